@@ -0,0 +1,98 @@
+use crate::{MegaUiRenderStats, MegaUiWindowRenderStats};
+use bevy::{
+    app::{AppBuilder, Plugin},
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    ecs::{IntoSystem, Res, ResMut},
+};
+
+/// Registers [MegaUiRenderStats] figures (summed over all windows) as diagnostics,
+/// so they can be logged with `LogDiagnosticsPlugin`.
+///
+/// Has to be added after [crate::MegaUiPlugin].
+pub struct MegaUiDiagnosticsPlugin;
+
+impl MegaUiDiagnosticsPlugin {
+    /// Number of vertices.
+    pub const VERTICES: DiagnosticId =
+        DiagnosticId::from_u128(121980544879860760442338833629718751999);
+    /// Number of indices.
+    pub const INDICES: DiagnosticId =
+        DiagnosticId::from_u128(300322205630849323060250836455461733043);
+    /// Number of draw calls.
+    pub const DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(157205428287017586244624632095436901198);
+    /// Number of uploaded textures.
+    pub const TEXTURE_UPLOADS: DiagnosticId =
+        DiagnosticId::from_u128(210610285739895877444760905431235469522);
+    /// Size of uploaded texture data in bytes.
+    pub const TEXTURE_UPLOAD_BYTES: DiagnosticId =
+        DiagnosticId::from_u128(148796459427271672849992588160444256477);
+    /// Number of live texture resources.
+    pub const TEXTURES: DiagnosticId =
+        DiagnosticId::from_u128(54576172801709905326211914977364190212);
+    /// Size of live texture resources in bytes.
+    pub const TEXTURE_BYTES: DiagnosticId =
+        DiagnosticId::from_u128(164889176943229677340712584247409880335);
+    /// Number of evicted textures.
+    pub const TEXTURE_EVICTIONS: DiagnosticId =
+        DiagnosticId::from_u128(113460129748873593454129669610300322385);
+    /// Size of vertex and index buffers in bytes.
+    pub const BUFFER_BYTES: DiagnosticId =
+        DiagnosticId::from_u128(301548939739327922890772207202715103569);
+
+    fn measurements(stats: &MegaUiWindowRenderStats) -> [(DiagnosticId, &'static str, usize); 9] {
+        [
+            (Self::VERTICES, "megaui_vertices", stats.vertices),
+            (Self::INDICES, "megaui_indices", stats.indices),
+            (Self::DRAW_CALLS, "megaui_draw_calls", stats.draw_calls),
+            (
+                Self::TEXTURE_UPLOADS,
+                "megaui_texture_uploads",
+                stats.texture_uploads,
+            ),
+            (
+                Self::TEXTURE_UPLOAD_BYTES,
+                "megaui_texture_upload_bytes",
+                stats.texture_upload_bytes,
+            ),
+            (Self::TEXTURES, "megaui_textures", stats.textures),
+            (
+                Self::TEXTURE_BYTES,
+                "megaui_texture_bytes",
+                stats.texture_bytes,
+            ),
+            (
+                Self::TEXTURE_EVICTIONS,
+                "megaui_texture_evictions",
+                stats.texture_evictions,
+            ),
+            (
+                Self::BUFFER_BYTES,
+                "megaui_buffer_bytes",
+                stats.buffer_bytes,
+            ),
+        ]
+    }
+
+    fn setup_system(mut diagnostics: ResMut<Diagnostics>) {
+        for (id, name, _) in &Self::measurements(&MegaUiWindowRenderStats::default()) {
+            diagnostics.add(Diagnostic::new(*id, *name, 20));
+        }
+    }
+
+    fn diagnostic_system(
+        mut diagnostics: ResMut<Diagnostics>,
+        render_stats: Res<MegaUiRenderStats>,
+    ) {
+        for (id, _, value) in &Self::measurements(&render_stats.total()) {
+            diagnostics.add_measurement(*id, *value as f64);
+        }
+    }
+}
+
+impl Plugin for MegaUiDiagnosticsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(Self::setup_system.system())
+            .add_system(Self::diagnostic_system.system());
+    }
+}
@@ -1,60 +1,156 @@
-use crate::{MegaUiContext, MegaUiSettings, WindowSize};
+use crate::{MegaUiContext, MegaUiContexts, MegaUiSettings, WindowSize, WindowSizes};
 use bevy::{
     app::Events,
+    asset::Assets,
     ecs::{Resources, World},
     input::{keyboard::KeyCode, mouse::MouseButton, Input},
-    window::{CursorMoved, ReceivedCharacter, Windows},
+    render::texture::Texture,
+    window::{CursorMoved, ReceivedCharacter, WindowId, Windows},
 };
+use megaui::Vector2;
+use std::collections::HashMap;
 
 // Is a thread local system because `megaui::Ui` (`MegaUiContext`) doesn't implement Send + Sync.
 pub fn process_input(_world: &mut World, resources: &mut Resources) {
     use megaui::InputHandler;
 
-    let mut ctx = resources.get_thread_local_mut::<MegaUiContext>().unwrap();
+    let mut primary_context = resources.get_thread_local_mut::<MegaUiContext>().unwrap();
+    let mut contexts = resources.get_thread_local_mut::<MegaUiContexts>().unwrap();
     let ev_cursor = resources.get::<Events<CursorMoved>>().unwrap();
     let ev_received_character = resources.get::<Events<ReceivedCharacter>>().unwrap();
     let mouse_button_input = resources.get::<Input<MouseButton>>().unwrap();
     let keyboard_input = resources.get::<Input<KeyCode>>().unwrap();
-    let mut window_size = resources.get_mut::<WindowSize>().unwrap();
+    let mut window_sizes = resources.get_mut::<WindowSizes>().unwrap();
     let windows = resources.get::<Windows>().unwrap();
     let megaui_settings = resources.get::<MegaUiSettings>().unwrap();
+    let mut texture_assets = resources.get_mut::<Assets<Texture>>().unwrap();
 
-    if let Some(window) = windows.get_primary() {
-        *window_size = WindowSize::new(
-            window.physical_width() as f32,
-            window.physical_height() as f32,
-            window.scale_factor() as f32,
+    let primary_context = &mut *primary_context;
+    let megaui_contexts = &mut *contexts;
+
+    for window in windows.iter() {
+        window_sizes.0.insert(
+            window.id(),
+            WindowSize::new(
+                window.physical_width() as f32,
+                window.physical_height() as f32,
+                window.scale_factor() as f32,
+            ),
         );
-    }
+        // The primary window context is created on building the plugin.
+        if !window.id().is_primary() {
+            megaui_contexts
+                .contexts
+                .entry(window.id())
+                .or_insert_with(|| MegaUiContext::new(&mut texture_assets));
+        }
 
-    if let Some(cursor_moved) = ctx.cursor.latest(&ev_cursor) {
-        if cursor_moved.id.is_primary() {
-            let scale_factor = megaui_settings.scale_factor as f32;
-            let mut mouse_position: (f32, f32) = (cursor_moved.position / scale_factor).into();
-            mouse_position.1 = window_size.height() / scale_factor - mouse_position.1;
-            ctx.mouse_position = mouse_position;
-            ctx.ui.mouse_move(mouse_position);
+        let layers = megaui_contexts.layers.entry(window.id()).or_default();
+        layers.retain(|name, _| {
+            megaui_settings
+                .layers
+                .iter()
+                .any(|layer| layer.name == *name)
+        });
+        for layer in &megaui_settings.layers {
+            layers
+                .entry(layer.name.clone())
+                .or_insert_with(|| MegaUiContext::new(&mut texture_assets));
         }
     }
 
-    let mouse_position = ctx.mouse_position;
-    if mouse_button_input.just_pressed(MouseButton::Left) {
-        ctx.ui.mouse_down(mouse_position);
+    let mut latest_cursor_positions = HashMap::<WindowId, _>::new();
+    for cursor_moved in megaui_contexts.cursor.iter(&ev_cursor) {
+        latest_cursor_positions.insert(cursor_moved.id, cursor_moved.position);
+        megaui_contexts.active_window = cursor_moved.id;
     }
-    if mouse_button_input.just_released(MouseButton::Left) {
-        ctx.ui.mouse_up(mouse_position);
+    for (window_id, position) in latest_cursor_positions {
+        let scale_factor = megaui_settings.scale_factor as f32;
+        let window_size = window_sizes.get(window_id);
+        let viewport = megaui_settings.viewport(window_id, &window_size);
+        // Cursor positions start at the bottom-left corner of a window.
+        let (x, y): (f32, f32) = position.into();
+        let mouse_position = [
+            (x - viewport.x) / scale_factor,
+            (window_size.height() - y - viewport.y) / scale_factor,
+        ];
+        // Widgets are drawn transformed, so the cursor is transformed back to hit them.
+        let [x, y] = megaui_settings
+            .transform(window_id)
+            .inverse()
+            .map_or(mouse_position, |inverse| {
+                inverse.transform_point(mouse_position)
+            });
+        let mouse_position = (x, y);
+        // Every layer tracks the cursor, so that they can tell whether it's hovered.
+        for (_, ctx) in
+            megaui_contexts.window_layers_mut(primary_context, window_id, &megaui_settings)
+        {
+            if ctx.visible {
+                ctx.mouse_position = mouse_position;
+                ctx.ui.mouse_move(mouse_position);
+            }
+        }
     }
 
     let shift = keyboard_input.pressed(KeyCode::LShift) || keyboard_input.pressed(KeyCode::RShift);
     let ctrl =
         keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
 
-    for event in ctx.received_character.iter(&ev_received_character) {
-        if event.id.is_primary() && !event.char.is_control() {
-            ctx.ui.char_event(event.char, shift, ctrl);
+    // Mouse presses and keyboard input are ignored while the UI is faded out completely.
+    let input_disabled = megaui_settings.is_input_disabled();
+
+    // Mouse buttons and keys aren't tied to a window, so we pass them to the window
+    // that has received cursor events last.
+    let active_window = megaui_contexts.active_window;
+
+    // A pressed mouse button moves the focus to the topmost visible layer under the cursor.
+    if mouse_button_input.just_pressed(MouseButton::Left) && !input_disabled {
+        megaui_contexts.focused_layer = megaui_contexts
+            .window_layers_mut(primary_context, active_window, &megaui_settings)
+            .into_iter()
+            .rev()
+            .find(|(_, ctx)| {
+                let (x, y) = ctx.mouse_position;
+                ctx.visible && ctx.ui.is_mouse_over(Vector2::new(x, y))
+            })
+            .and_then(|(layer, _)| layer);
+    }
+
+    let received_characters = megaui_contexts
+        .received_character
+        .iter(&ev_received_character)
+        .filter(|event| !event.char.is_control() && !input_disabled)
+        .map(|event| (event.id, event.char))
+        .collect::<Vec<_>>();
+    for (window_id, char) in received_characters {
+        let ctx = if window_id == active_window {
+            focused_context(megaui_contexts, primary_context)
+        } else {
+            megaui_contexts
+                .context_mut(primary_context, window_id)
+                .filter(|ctx| ctx.visible)
+        };
+        if let Some(ctx) = ctx {
+            ctx.ui.char_event(char, shift, ctrl);
         }
     }
 
+    let ctx = match focused_context(megaui_contexts, primary_context) {
+        Some(ctx) => ctx,
+        None => return,
+    };
+
+    process_mouse_buttons(
+        &mut ctx.ui,
+        ctx.mouse_position,
+        &mouse_button_input,
+        input_disabled,
+    );
+    if input_disabled {
+        return;
+    }
+
     if keyboard_input.pressed(KeyCode::Up) {
         ctx.ui.key_down(megaui::KeyCode::Up, shift, ctrl);
     }
@@ -104,3 +200,86 @@ pub fn process_input(_world: &mut World, resources: &mut Resources) {
         ctx.ui.key_down(megaui::KeyCode::A, shift, ctrl);
     }
 }
+
+// Passes the left mouse button to the UI. Presses are ignored while input is disabled, but releases
+// are still delivered, so that drags started before the UI has faded out end.
+fn process_mouse_buttons(
+    ui: &mut megaui::Ui,
+    mouse_position: (f32, f32),
+    mouse_button_input: &Input<MouseButton>,
+    input_disabled: bool,
+) {
+    use megaui::InputHandler;
+
+    if mouse_button_input.just_pressed(MouseButton::Left) && !input_disabled {
+        ui.mouse_down(mouse_position);
+    }
+    if mouse_button_input.just_released(MouseButton::Left) {
+        ui.mouse_up(mouse_position);
+    }
+}
+
+// Returns the context of the active window that receives mouse button and keyboard input.
+// Falls back to the default context if the focused layer has been removed or hidden, hidden
+// default contexts don't receive input either.
+fn focused_context<'a>(
+    megaui_contexts: &'a mut MegaUiContexts,
+    primary_context: &'a mut MegaUiContext,
+) -> Option<&'a mut MegaUiContext> {
+    let MegaUiContexts {
+        contexts,
+        layers,
+        active_window,
+        focused_layer,
+        ..
+    } = megaui_contexts;
+
+    let focused = match (focused_layer, layers.get_mut(&*active_window)) {
+        (Some(focused_layer), Some(layers)) => {
+            layers.get_mut(&*focused_layer).filter(|ctx| ctx.visible)
+        }
+        _ => None,
+    };
+    match focused {
+        Some(ctx) => Some(ctx),
+        None if active_window.is_primary() => Some(primary_context).filter(|ctx| ctx.visible),
+        None => contexts.get_mut(&*active_window).filter(|ctx| ctx.visible),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::draw_frame;
+    use megaui::InputHandler;
+
+    #[test]
+    fn test_release_while_input_disabled() {
+        let mut ui = megaui::Ui::new();
+        let mut mouse_button_input = Input::<MouseButton>::default();
+        draw_frame(&mut ui);
+
+        // Starts dragging the window by its titlebar.
+        ui.mouse_move((20.0, 15.0));
+        mouse_button_input.press(MouseButton::Left);
+        process_mouse_buttons(&mut ui, (20.0, 15.0), &mouse_button_input, false);
+        draw_frame(&mut ui);
+        ui.mouse_move((60.0, 45.0));
+        draw_frame(&mut ui);
+
+        // The button is released while the UI is faded out.
+        mouse_button_input.update();
+        mouse_button_input.release(MouseButton::Left);
+        process_mouse_buttons(&mut ui, (60.0, 45.0), &mouse_button_input, true);
+        draw_frame(&mut ui);
+
+        // Once the UI fades in, moving the cursor doesn't drag the window anymore.
+        mouse_button_input.update();
+        ui.mouse_move((80.0, 55.0));
+        process_mouse_buttons(&mut ui, (80.0, 55.0), &mouse_button_input, false);
+        assert_eq!(
+            draw_frame(&mut ui),
+            Some(megaui::Rect::new(50.0, 40.0, 100.0, 50.0))
+        );
+    }
+}
@@ -54,20 +54,36 @@
 //!
 //! - [`bevy_egui`](https://github.com/mvlabat/bevy_egui)
 
+pub use crate::diagnostics::MegaUiDiagnosticsPlugin;
 pub use megaui;
 
+mod diagnostics;
 mod input;
 mod megaui_node;
+#[cfg(feature = "rasterizer")]
+pub mod rasterizer;
+#[cfg(feature = "snapshots")]
+pub mod snapshot;
+#[cfg(test)]
+mod test_utils;
+mod texture;
 mod transform_node;
+mod window_texture_node;
 
-use crate::{input::process_input, megaui_node::MegaUiNode, transform_node::MegaUiTransformNode};
+use crate::{
+    input::process_input,
+    megaui_node::{MegaUiNode, MegaUiPassAttachments},
+    transform_node::MegaUiTransformNode,
+    window_texture_node::MegaUiWindowTextureNode,
+};
 use bevy::{
-    app::{stage, AppBuilder, EventReader, Plugin},
-    asset::{Assets, Handle, HandleUntyped},
-    ecs::IntoSystem,
+    app::{stage, AppBuilder, EventReader, Events, Plugin},
+    asset::{AssetServer, Assets, Handle, HandleUntyped},
+    ecs::{IntoSystem, Local, Res, ResMut},
     log,
     reflect::TypeUuid,
     render::{
+        pass::{LoadOp, Operations},
         pipeline::{
             BlendDescriptor, BlendFactor, BlendOperation, ColorStateDescriptor, ColorWrite,
             CompareFunction, CullMode, DepthStencilStateDescriptor, FrontFace, IndexFormat,
@@ -76,18 +92,39 @@ use bevy::{
         },
         render_graph::{base, base::Msaa, RenderGraph, WindowSwapChainNode, WindowTextureNode},
         shader::{Shader, ShaderStage, ShaderStages},
-        texture::{Extent3d, Texture, TextureDimension, TextureFormat},
+        texture::{
+            Extent3d, FilterMode, SamplerDescriptor, Texture, TextureDescriptor, TextureDimension,
+            TextureFormat, TextureUsage,
+        },
     },
-    window::{CursorMoved, ReceivedCharacter},
+    sprite::TextureAtlas,
+    window::{CursorMoved, ReceivedCharacter, WindowCreated, WindowId},
 };
 use megaui::Vector2;
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
 /// A handle pointing to the megaui [PipelineDescriptor].
 pub const MEGAUI_PIPELINE_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 9404026720151354217);
+/// A handle pointing to the [PipelineDescriptor] compositing the UI rendered at a reduced
+/// resolution, see [MegaUiSettings::resolution_scale].
+pub const MEGAUI_COMPOSITE_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 2763117350519423108);
+/// A handle pointing to the [PipelineDescriptor] drawing blurred window backgrounds,
+/// see [MegaUiSettings::background_blur].
+pub const MEGAUI_BLUR_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 12150386311795632473);
+/// A handle pointing to the [PipelineDescriptor] drawing window shadows,
+/// see [MegaUiSettings::window_decorations].
+pub const MEGAUI_SHADOW_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 6839170142453265881);
 /// Name of the transform uniform.
 pub const MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME: &str = "MegaUiTransform";
+/// Name of the optional uniform with [MegaUiSettings::transforms] of the window.
+pub const MEGAUI_AFFINE_TRANSFORM_RESOURCE_BINDING_NAME: &str = "MegaUiAffineTransform";
+/// Name of the optional uniform with [MegaUiSettings::opacity] and [MegaUiSettings::color_filter]
+/// of the window.
+pub const MEGAUI_COLOR_TRANSFORM_RESOURCE_BINDING_NAME: &str = "MegaUiColorTransform";
 /// Name of the texture uniform.
 pub const MEGAUI_TEXTURE_RESOURCE_BINDING_NAME: &str = "MegaUiTexture_texture";
 
@@ -111,44 +148,921 @@ pub struct MegaUiSettings {
     /// }
     /// ```
     pub scale_factor: f64,
+    /// Fraction of the physical window resolution the UI is rendered at (`1.0` by default).
+    ///
+    /// Values below `1.0` make the UI render into an intermediate texture of the reduced size,
+    /// which is then composited onto the window with [MegaUiSettings::upscale_filter]. This saves
+    /// fill rate on high-DPI displays at the cost of blurrier widgets. Values above `1.0` are
+    /// treated as `1.0`.
+    ///
+    /// The setting is ignored if [MegaUiRenderGraphConfig::depth_mode] isn't
+    /// [MegaUiDepthMode::None], as the scene depth texture has the full resolution.
+    pub resolution_scale: f32,
+    /// The filter used for compositing the UI rendered at a reduced resolution
+    /// ([FilterMode::Linear] by default).
+    pub upscale_filter: FilterMode,
+    /// Draws blurred scene color behind the window rects of the primary window (`None`
+    /// by default), see [MegaUiBackgroundBlur].
+    pub background_blur: Option<MegaUiBackgroundBlur>,
+    /// Draws drop shadows and rounded corners of the window rects (`None` by default),
+    /// see [MegaUiWindowDecorations].
+    pub window_decorations: Option<MegaUiWindowDecorations>,
+    /// Viewports confining the UI of windows to sub-rects (empty by default),
+    /// see [MegaUiViewport].
+    pub viewports: HashMap<WindowId, MegaUiViewport>,
+    /// 2D transforms applied to the UI of windows, such as zoom, pan or rotation (empty
+    /// by default), see [MegaUiAffineTransform].
+    ///
+    /// Pointer positions are transformed back, so that widgets can still be interacted with.
+    /// Clipping zones are transformed to their bounding boxes, as scissor rects can't be rotated.
+    pub transforms: HashMap<WindowId, MegaUiAffineTransform>,
+    /// Named context layers of each window (empty by default), see [MegaUiLayer].
+    pub layers: Vec<MegaUiLayer>,
+    /// Opacity of the whole UI, from `0.0` to `1.0` (`1.0` by default).
+    ///
+    /// Is applied in the shaders on top of widget colors, so that the UI can be faded in and out.
+    pub opacity: f32,
+    /// Whether mouse button presses and keyboard input are ignored while [MegaUiSettings::opacity]
+    /// is zero (`true` by default). Releases are still delivered, so drags end as usual.
+    pub disable_input_when_transparent: bool,
+    /// A color transform applied to the UI output, such as a simulation of color vision
+    /// deficiencies (`None` by default), see [MegaUiColorFilter].
+    pub color_filter: Option<MegaUiColorFilter>,
+    /// GPU memory that megaui textures of each window may occupy, in bytes (`None` by default,
+    /// which means unlimited).
+    ///
+    /// If textures exceed the budget, the least recently drawn ones are evicted. Evicted textures
+    /// stay registered and are uploaded again as soon as they are drawn. Textures drawn during
+    /// the current frame are never evicted, so the budget may be exceeded temporarily.
+    pub texture_memory_budget: Option<usize>,
+    /// Limits texture uploads of each window per frame (`None` by default, which means
+    /// unlimited), see [MegaUiTextureUploadBudget].
+    ///
+    /// Textures that don't fit into the budget are queued until the next frames, drawn textures
+    /// are uploaded first. Until a queued texture is uploaded, it's drawn as a grey placeholder.
+    /// Textures that are still loading (or have failed to load) aren't drawn.
+    pub texture_upload_budget: Option<MegaUiTextureUploadBudget>,
 }
 
 impl Default for MegaUiSettings {
     fn default() -> Self {
-        Self { scale_factor: 1.0 }
+        Self {
+            scale_factor: 1.0,
+            resolution_scale: 1.0,
+            upscale_filter: FilterMode::Linear,
+            background_blur: None,
+            window_decorations: None,
+            viewports: HashMap::new(),
+            transforms: HashMap::new(),
+            layers: Vec::new(),
+            opacity: 1.0,
+            disable_input_when_transparent: true,
+            color_filter: None,
+            texture_memory_budget: None,
+            texture_upload_budget: None,
+        }
     }
 }
 
-/// A resource that is used to store `bevy_megaui` context.
+impl MegaUiSettings {
+    // Returns the viewport of a window, which covers the whole window if it isn't configured.
+    fn viewport(&self, window_id: WindowId, window_size: &WindowSize) -> MegaUiViewport {
+        self.viewports
+            .get(&window_id)
+            .copied()
+            .unwrap_or_else(|| MegaUiViewport {
+                x: 0.0,
+                y: 0.0,
+                width: window_size.width(),
+                height: window_size.height(),
+            })
+    }
+
+    // Returns the z-order of a layer, `None` stands for the default context. Layers with
+    // the same order are drawn in the order they are listed, after the default context.
+    fn layer_order(&self, layer: Option<&str>) -> (i32, Option<usize>) {
+        let layer = match layer {
+            Some(layer) => layer,
+            None => return (0, None),
+        };
+        self.layers
+            .iter()
+            .position(|configured_layer| configured_layer.name == layer)
+            .map_or((i32::MAX, None), |index| {
+                (self.layers[index].order, Some(index))
+            })
+    }
+
+    fn opacity(&self) -> f32 {
+        self.opacity.max(0.0).min(1.0)
+    }
+
+    fn is_input_disabled(&self) -> bool {
+        self.disable_input_when_transparent && self.opacity() <= 0.0
+    }
+
+    fn transform(&self, window_id: WindowId) -> MegaUiAffineTransform {
+        self.transforms
+            .get(&window_id)
+            .copied()
+            .unwrap_or(MegaUiAffineTransform::IDENTITY)
+    }
+}
+
+/// A named context layer, see [MegaUiSettings::layers].
+///
+/// Each window gets a separate [MegaUiContext] for every layer, with its own [megaui::Ui]
+/// and textures (see [MegaUiContexts::layer_mut]), so that layers can be hidden
+/// ([MegaUiContext::set_visible]) independently.
+///
+/// Layers are drawn in the ascending order, the default context of a window has the order
+/// of `0`. Mouse buttons and keyboard input go to the topmost visible layer under the cursor
+/// when a mouse button is pressed (or to the default context if there's none).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MegaUiLayer {
+    /// A unique name of the layer.
+    pub name: Cow<'static, str>,
+    /// Z-order of the layer.
+    pub order: i32,
+}
+
+impl MegaUiLayer {
+    /// Creates a layer.
+    pub fn new(name: impl Into<Cow<'static, str>>, order: i32) -> Self {
+        Self {
+            name: name.into(),
+            order,
+        }
+    }
+}
+
+/// A per-frame limit of texture uploads, see [MegaUiSettings::texture_upload_budget].
+///
+/// At least one texture is uploaded per frame regardless of the budget, and a texture is
+/// uploaded as a whole, so the budget may be exceeded by the last upload of a frame. Updates
+/// of modified texture assets aren't queued, but they consume the budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MegaUiTextureUploadBudget {
+    /// Maximum size of uploaded texture data in bytes (including mip levels).
+    Bytes(usize),
+    /// Maximum number of uploaded textures.
+    Count(usize),
+}
+
+/// A color transform of the UI output, see [MegaUiSettings::color_filter].
+///
+/// It's applied to linear RGB colors in the fragment stage of the megaui pass, so the scene
+/// and window effects, such as [MegaUiSettings::background_blur], stay untouched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MegaUiColorFilter {
+    /// Simulates protanopia (missing red cones).
+    Protanopia,
+    /// Simulates deuteranopia (missing green cones).
+    Deuteranopia,
+    /// Simulates tritanopia (missing blue cones).
+    Tritanopia,
+    /// Doubles the contrast around mid-grey.
+    HighContrast,
+    /// An affine transform: each row holds weights of the red, green and blue inputs of an output
+    /// channel followed by an offset.
+    Matrix([[f32; 4]; 3]),
+}
+
+impl MegaUiColorFilter {
+    /// The matrix of the filter, see [MegaUiColorFilter::Matrix].
+    pub fn matrix(&self) -> [[f32; 4]; 3] {
+        // The simulation matrices are taken from "A Physiologically-based Model for Simulation
+        // of Color Vision Deficiency" by Machado et al. (severity 1.0).
+        match *self {
+            MegaUiColorFilter::Protanopia => [
+                [0.152_286, 1.052_583, -0.204_868, 0.0],
+                [0.114_503, 0.786_281, 0.099_216, 0.0],
+                [-0.003_882, -0.048_116, 1.051_998, 0.0],
+            ],
+            MegaUiColorFilter::Deuteranopia => [
+                [0.367_322, 0.860_646, -0.227_968, 0.0],
+                [0.280_085, 0.672_501, 0.047_413, 0.0],
+                [-0.011_820, 0.042_940, 0.968_881, 0.0],
+            ],
+            MegaUiColorFilter::Tritanopia => [
+                [1.255_528, -0.076_749, -0.178_779, 0.0],
+                [-0.078_411, 0.930_809, 0.147_602, 0.0],
+                [0.004_733, 0.691_367, 0.303_900, 0.0],
+            ],
+            MegaUiColorFilter::HighContrast => [
+                [2.0, 0.0, 0.0, -0.5],
+                [0.0, 2.0, 0.0, -0.5],
+                [0.0, 0.0, 2.0, -0.5],
+            ],
+            MegaUiColorFilter::Matrix(matrix) => matrix,
+        }
+    }
+}
+
+/// A 2D affine transform of UI coordinates, see [MegaUiSettings::transforms].
+///
+/// It's applied to UI units before they are mapped to the viewport. The Y axis points down,
+/// so positive angles rotate clockwise on the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MegaUiAffineTransform {
+    /// The image of the X axis.
+    pub x_axis: [f32; 2],
+    /// The image of the Y axis.
+    pub y_axis: [f32; 2],
+    /// Translation.
+    pub translation: [f32; 2],
+}
+
+impl Default for MegaUiAffineTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl MegaUiAffineTransform {
+    /// The identity transform.
+    pub const IDENTITY: Self = Self {
+        x_axis: [1.0, 0.0],
+        y_axis: [0.0, 1.0],
+        translation: [0.0, 0.0],
+    };
+
+    /// Creates a translation.
+    pub fn from_translation(translation: [f32; 2]) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Creates a scale around the origin.
+    pub fn from_scale(scale: f32) -> Self {
+        Self {
+            x_axis: [scale, 0.0],
+            y_axis: [0.0, scale],
+            translation: [0.0, 0.0],
+        }
+    }
+
+    /// Creates a rotation around the origin, the angle is in radians.
+    pub fn from_angle(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            x_axis: [cos, sin],
+            y_axis: [-sin, cos],
+            translation: [0.0, 0.0],
+        }
+    }
+
+    /// Returns a transform that applies `other` first and then `self`.
+    pub fn mul_transform(&self, other: &Self) -> Self {
+        Self {
+            x_axis: self.transform_vector(other.x_axis),
+            y_axis: self.transform_vector(other.y_axis),
+            translation: self.transform_point(other.translation),
+        }
+    }
+
+    /// Transforms a point.
+    pub fn transform_point(&self, point: [f32; 2]) -> [f32; 2] {
+        let [x, y] = self.transform_vector(point);
+        [x + self.translation[0], y + self.translation[1]]
+    }
+
+    /// Transforms a vector (ignoring the translation).
+    pub fn transform_vector(&self, vector: [f32; 2]) -> [f32; 2] {
+        [
+            self.x_axis[0] * vector[0] + self.y_axis[0] * vector[1],
+            self.x_axis[1] * vector[0] + self.y_axis[1] * vector[1],
+        ]
+    }
+
+    /// Returns the inverse transform, or `None` if the transform is degenerate.
+    pub fn inverse(&self) -> Option<Self> {
+        let determinant = self.x_axis[0] * self.y_axis[1] - self.y_axis[0] * self.x_axis[1];
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+        let inverse = Self {
+            x_axis: [self.y_axis[1] / determinant, -self.x_axis[1] / determinant],
+            y_axis: [-self.y_axis[0] / determinant, self.x_axis[0] / determinant],
+            translation: [0.0, 0.0],
+        };
+        let [x, y] = inverse.transform_vector(self.translation);
+        Some(Self {
+            translation: [-x, -y],
+            ..inverse
+        })
+    }
+}
+
+/// A rect the UI of a window is confined to, in logical pixels relative to the top-left corner
+/// of the window (see [MegaUiSettings::viewports]).
+///
+/// UI coordinates start at the top-left corner of the viewport, and the UI is clipped by it.
+/// Pointer positions are converted accordingly.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MegaUiViewport {
+    /// Left edge.
+    pub x: f32,
+    /// Top edge.
+    pub y: f32,
+    /// Width.
+    pub width: f32,
+    /// Height.
+    pub height: f32,
+}
+
+/// Drop shadows and anti-aliased rounded corners of megaui windows, generated from the window
+/// rects (see [MegaUiContext::window_rects]).
+///
+/// Shadows are drawn before the UI geometry. Rounded corners are cut from the geometry of each
+/// window as it's drawn, so they don't affect the windows drawn under or on top of it and work
+/// with any [MegaUiRenderGraphConfig::depth_mode]. Draw lists are assigned to windows by the draw
+/// order: a window owns the draw lists from its background up to the next window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MegaUiWindowDecorations {
+    /// Corner radius in UI units (`4.0` by default), `0.0` disables rounded corners.
+    pub corner_radius: f32,
+    /// Shadow offset in UI units (`[2.0, 4.0]` by default).
+    pub shadow_offset: [f32; 2],
+    /// Shadow blur radius in UI units (`8.0` by default).
+    pub shadow_blur: f32,
+    /// Shadow RGBA color (`[0.0, 0.0, 0.0, 0.4]` by default), zero alpha disables shadows.
+    pub shadow_color: [f32; 4],
+}
+
+impl Default for MegaUiWindowDecorations {
+    fn default() -> Self {
+        Self {
+            corner_radius: 4.0,
+            shadow_offset: [2.0, 4.0],
+            shadow_blur: 8.0,
+            shadow_color: [0.0, 0.0, 0.0, 0.4],
+        }
+    }
+}
+
+impl MegaUiWindowDecorations {
+    fn has_shadows(&self) -> bool {
+        self.shadow_color[3] > 0.0
+    }
+}
+
+/// A frosted-glass effect: the scene color under each megaui window is blurred and drawn
+/// as the window background before the UI geometry.
+///
+/// Only the primary window backgrounds are blurred. The scene color is copied from
+/// [MegaUiRenderGraphConfig::color_source] each frame, so the source texture has to be created
+/// with [TextureUsage::COPY_SRC] and has to match the window size and the default texture format.
+///
+/// **The default color source is the swap chain texture, which can't be copied**, so the effect
+/// requires the scene to be rendered into a texture (as it's usually done for post-processing)
+/// and [MegaUiRenderGraphConfig::color_source] to point to it. If the color source can't be
+/// copied, a warning is logged once and backgrounds aren't blurred.
+///
+/// Window rects are recorded by [MegaUiContext::draw_window], the ones of the windows drawn
+/// otherwise can be added with [MegaUiContext::add_window_rect]. Backgrounds are visible only
+/// through translucent megaui window styles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MegaUiBackgroundBlur {
+    /// Blur radius in UI units (`8.0` by default).
+    pub radius: f32,
+    /// An RGBA color mixed into the blurred color according to its alpha
+    /// (`[1.0, 1.0, 1.0, 0.2]` by default).
+    pub tint: [f32; 4],
+}
+
+impl Default for MegaUiBackgroundBlur {
+    fn default() -> Self {
+        Self {
+            radius: 8.0,
+            tint: [1.0, 1.0, 1.0, 0.2],
+        }
+    }
+}
+
+/// A resource containing UI rendering statistics of the last rendered frame.
+///
+/// Add [MegaUiDiagnosticsPlugin] to register the figures as diagnostics.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MegaUiRenderStats {
+    /// Statistics of each window, filled by its megaui pass. Entries of the windows that aren't
+    /// rendered (for instance, closed ones) are removed.
+    pub windows: HashMap<WindowId, MegaUiWindowRenderStats>,
+}
+
+impl MegaUiRenderStats {
+    /// Returns statistics summed over all windows.
+    pub fn total(&self) -> MegaUiWindowRenderStats {
+        self.windows
+            .values()
+            .fold(MegaUiWindowRenderStats::default(), |total, stats| {
+                MegaUiWindowRenderStats {
+                    vertices: total.vertices + stats.vertices,
+                    indices: total.indices + stats.indices,
+                    draw_calls: total.draw_calls + stats.draw_calls,
+                    texture_uploads: total.texture_uploads + stats.texture_uploads,
+                    texture_upload_bytes: total.texture_upload_bytes + stats.texture_upload_bytes,
+                    textures: total.textures + stats.textures,
+                    texture_bytes: total.texture_bytes + stats.texture_bytes,
+                    texture_evictions: total.texture_evictions + stats.texture_evictions,
+                    buffer_bytes: total.buffer_bytes + stats.buffer_bytes,
+                }
+            })
+    }
+}
+
+/// UI rendering statistics of a window, see [MegaUiRenderStats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MegaUiWindowRenderStats {
+    /// Number of vertices.
+    pub vertices: usize,
+    /// Number of indices.
+    pub indices: usize,
+    /// Number of draw calls.
+    pub draw_calls: usize,
+    /// Number of textures uploaded (created or updated) during the frame.
+    pub texture_uploads: usize,
+    /// Size of texture data uploaded during the frame in bytes (including mip levels).
+    pub texture_upload_bytes: usize,
+    /// Number of live texture resources.
+    pub textures: usize,
+    /// Size of live texture resources in bytes (including mip levels).
+    pub texture_bytes: usize,
+    /// Number of textures evicted during the frame, see [MegaUiSettings::texture_memory_budget].
+    pub texture_evictions: usize,
+    /// Size of vertex and index buffers in bytes.
+    pub buffer_bytes: usize,
+}
+
+/// A resource defining how the megaui pass of the primary window is wired into the render graph.
+///
+/// Insert it before adding [MegaUiPlugin] if the UI needs to be rendered after other passes
+/// (post-processing, `bevy_ui`, etc.) or into a custom pass:
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_megaui::{MegaUiPlugin, MegaUiRenderGraphConfig};
+///
+/// fn main() {
+///     let mut render_graph_config = MegaUiRenderGraphConfig::default();
+///     // Render the UI on top of `bevy_ui`.
+///     render_graph_config.dependencies.push("ui_pass".into());
+///
+///     App::build()
+///         .add_plugins(DefaultPlugins)
+///         .add_resource(render_graph_config)
+///         .add_plugin(MegaUiPlugin)
+///         .run();
+/// }
+/// ```
+///
+/// The plugin panics while being built if any of the configured nodes are missing from
+/// the render graph, so make sure the plugins adding them are added before [MegaUiPlugin].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MegaUiRenderGraphConfig {
+    /// Nodes the megaui pass runs after (`base::node::MAIN_PASS` by default).
+    pub dependencies: Vec<Cow<'static, str>>,
+    /// The color texture the UI is rendered to, it's also used as a resolve target
+    /// if MSAA is enabled (`base::node::PRIMARY_SWAP_CHAIN` by default).
+    pub color_source: RenderGraphSlot,
+    /// The multisampled color texture, it's read only if MSAA is enabled
+    /// (`base::node::MAIN_SAMPLED_COLOR_ATTACHMENT` by default).
+    ///
+    /// The texture is expected to contain the scene and to match the [Msaa] sample count.
+    /// The base render graph nodes don't follow runtime changes of [Msaa], so the app has to
+    /// re-create the node with the new sample count (or add it, if MSAA gets enabled).
+    /// The megaui pass is reconnected to the node once it exists.
+    pub sampled_color_source: RenderGraphSlot,
+    /// The depth texture, it's read only if [MegaUiRenderGraphConfig::depth_mode] isn't
+    /// [MegaUiDepthMode::None] (`base::node::MAIN_DEPTH_TEXTURE` by default).
+    ///
+    /// With [MegaUiDepthMode::ClearAndWrite] the default is replaced with the megaui-owned
+    /// [node::MEGAUI_DEPTH_TEXTURE], which follows the [Msaa] sample count. With
+    /// [MegaUiDepthMode::ReadOnly] the scene depth is needed, so the UI isn't rendered
+    /// (and an error is logged) while the [Msaa] sample count differs from the one
+    /// the base render graph has been built with.
+    pub depth_source: RenderGraphSlot,
+    /// Defines how the megaui pass uses the depth texture ([MegaUiDepthMode::None] by default).
+    pub depth_mode: MegaUiDepthMode,
+    /// Nodes of secondary windows that are rendered by the app (empty by default).
+    ///
+    /// Windows without an entry get their own swap chain node ([node::MEGAUI_SWAP_CHAIN]), which
+    /// is cleared with [ClearColor](bevy::render::pass::ClearColor) before the UI is drawn.
+    /// If the app renders to a window, its swap chain node has to be configured, as a swap chain
+    /// frame can't be acquired twice.
+    /// Entries are read once a window is created, so they have to be inserted beforehand.
+    pub secondary_windows: HashMap<WindowId, MegaUiWindowRenderGraphConfig>,
+}
+
+impl Default for MegaUiRenderGraphConfig {
+    fn default() -> Self {
+        Self {
+            dependencies: vec![base::node::MAIN_PASS.into()],
+            color_source: RenderGraphSlot::new(
+                base::node::PRIMARY_SWAP_CHAIN,
+                WindowSwapChainNode::OUT_TEXTURE,
+            ),
+            sampled_color_source: RenderGraphSlot::new(
+                base::node::MAIN_SAMPLED_COLOR_ATTACHMENT,
+                WindowTextureNode::OUT_TEXTURE,
+            ),
+            depth_source: RenderGraphSlot::new(
+                base::node::MAIN_DEPTH_TEXTURE,
+                WindowTextureNode::OUT_TEXTURE,
+            ),
+            depth_mode: MegaUiDepthMode::None,
+            secondary_windows: HashMap::new(),
+        }
+    }
+}
+
+/// Render graph nodes the megaui pass of a secondary window is connected to,
+/// see [MegaUiRenderGraphConfig::secondary_windows].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MegaUiWindowRenderGraphConfig {
+    /// Nodes the megaui pass runs after, such as the pass rendering the window scene.
+    pub dependencies: Vec<Cow<'static, str>>,
+    /// The color texture the UI is rendered to, usually the window swap chain node output.
+    pub color_source: RenderGraphSlot,
+}
+
+/// A resource for overriding the shaders or the whole pipeline of the megaui pass.
+///
+/// Insert it before adding [MegaUiPlugin]. Custom shaders or pipelines have to keep the contract
+/// of the embedded ones:
+/// - vertex attributes are `Vertex_Position` (`vec3`), `Vertex_Uv` (`vec2`)
+///   and `Vertex_Color` (`vec4`);
+/// - the transform uniform block is named [MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME], it's bound
+///   at the binding 0 of the group 0 and declares `vec2 scale` and `vec2 translation`;
+/// - the texture is named [MEGAUI_TEXTURE_RESOURCE_BINDING_NAME] and is bound in a separate group
+///   together with its sampler.
+///
+/// Shaders may also declare the optional uniform blocks of the group 0, those that don't are left
+/// without the corresponding features:
+/// - [MEGAUI_AFFINE_TRANSFORM_RESOURCE_BINDING_NAME] at the binding 1 with `vec2 ui_x_axis`,
+///   `vec2 ui_y_axis` and `vec2 ui_translation` of [MegaUiSettings::transforms];
+/// - [MEGAUI_COLOR_TRANSFORM_RESOURCE_BINDING_NAME] at the binding 2 with `vec4 ui_color_scale`
+///   that colors are multiplied by to apply [MegaUiSettings::opacity], and `mat4 ui_color_matrix`
+///   of [MegaUiSettings::color_filter].
+///
+/// Rounded corners of [MegaUiSettings::window_decorations] are cut by shaders that declare
+/// the optional `Vertex_WindowRect` (`vec4`, the center and the half size of the window
+/// the vertex belongs to) and `Vertex_CornerRadius` (`float`) vertex attributes.
+#[derive(Clone, Debug, Default)]
+pub struct MegaUiPipelineConfig {
+    /// A vertex shader replacing the embedded one (`None` by default).
+    pub vertex_shader: Option<Handle<Shader>>,
+    /// A fragment shader replacing the embedded one (`None` by default).
+    pub fragment_shader: Option<Handle<Shader>>,
+    /// A pipeline replacing the one built by the plugin (`None` by default).
+    ///
+    /// If it's set, the shader overrides are ignored. Sample count, index format and vertex buffer
+    /// layout are specialized by the plugin, but the depth stencil state has to match
+    /// [MegaUiRenderGraphConfig::depth_mode].
+    pub pipeline_descriptor: Option<PipelineDescriptor>,
+    /// A development option for loading the shaders with the [AssetServer] and watching them
+    /// for changes (`None` by default).
+    ///
+    /// The pipeline gets recompiled each time the shaders are modified. If a shader fails
+    /// to compile, the embedded (or overridden) shaders are used instead.
+    pub hot_reload_shaders: Option<MegaUiShaderPaths>,
+    /// Defines how the UI is blended with the render target ([MegaUiBlendMode::Straight]
+    /// by default).
+    ///
+    /// If [MegaUiPipelineConfig::pipeline_descriptor] is set, its blend state has to match
+    /// the blend mode, as textures and vertex colors are still converted according to it.
+    pub blend_mode: MegaUiBlendMode,
+}
+
+/// Asset paths of the megaui shaders, see [MegaUiPipelineConfig::hot_reload_shaders].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MegaUiShaderPaths {
+    /// Vertex shader path.
+    pub vertex: String,
+    /// Fragment shader path.
+    pub fragment: String,
+}
+
+struct HotReloadedShaders {
+    vertex: Handle<Shader>,
+    fragment: Handle<Shader>,
+}
+
+/// Defines how the UI is blended with the render target.
+///
+/// Textures are converted on upload to the alpha representation the blend mode expects,
+/// see [MegaUiTextureParams::premultiplied].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MegaUiBlendMode {
+    /// Colors with straight (not premultiplied) alpha are blended over the target.
+    Straight,
+    /// Colors with premultiplied alpha are blended over the target. Unlike
+    /// [MegaUiBlendMode::Straight], this blends filtered textures without dark fringes.
+    Premultiplied,
+    /// Colors multiplied by their alpha are added to the target, the target alpha is left as is.
+    Additive,
+}
+
+impl Default for MegaUiBlendMode {
+    fn default() -> Self {
+        MegaUiBlendMode::Straight
+    }
+}
+
+impl MegaUiBlendMode {
+    fn premultiplied_alpha(self) -> bool {
+        self == MegaUiBlendMode::Premultiplied
+    }
+
+    // Returns color and alpha blend descriptors. Alpha is composited with the "over" operator
+    // (except for the additive mode), so that rendering into transparent targets gives correct
+    // coverage.
+    fn blend_descriptors(self) -> (BlendDescriptor, BlendDescriptor) {
+        let over = || BlendDescriptor {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::OneMinusSrcAlpha,
+            operation: BlendOperation::Add,
+        };
+        match self {
+            MegaUiBlendMode::Straight => (
+                BlendDescriptor {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                over(),
+            ),
+            MegaUiBlendMode::Premultiplied => (over(), over()),
+            MegaUiBlendMode::Additive => (
+                BlendDescriptor {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                BlendDescriptor {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            ),
+        }
+    }
+
+    // The UI rendered into a transparent target always ends up with premultiplied colors
+    // (or with zero alpha in the additive mode), so it's composited accordingly.
+    fn composite_blend_descriptors(self) -> (BlendDescriptor, BlendDescriptor) {
+        match self {
+            MegaUiBlendMode::Straight | MegaUiBlendMode::Premultiplied => {
+                MegaUiBlendMode::Premultiplied.blend_descriptors()
+            }
+            MegaUiBlendMode::Additive => (
+                BlendDescriptor {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                BlendDescriptor {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            ),
+        }
+    }
+}
+
+/// Defines how the megaui pass uses the depth texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MegaUiDepthMode {
+    /// The pass has no depth attachment, the depth texture is left untouched.
+    None,
+    /// The UI is depth tested against the scene depth, but doesn't write to the depth texture.
+    ReadOnly,
+    /// The depth texture is cleared before the pass and written to by the UI.
+    ClearAndWrite,
+}
+
+impl MegaUiDepthMode {
+    // Secondary windows don't have a scene depth texture to test against, so their megaui-owned
+    // depth textures get cleared in any mode.
+    fn depth_operations(self, has_scene_depth: bool) -> Option<Operations<f32>> {
+        match self {
+            MegaUiDepthMode::None => None,
+            MegaUiDepthMode::ReadOnly if has_scene_depth => Some(Operations {
+                load: LoadOp::Load,
+                store: true,
+            }),
+            MegaUiDepthMode::ReadOnly | MegaUiDepthMode::ClearAndWrite => Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+        }
+    }
+
+    fn depth_stencil_state(self) -> Option<DepthStencilStateDescriptor> {
+        let depth_write_enabled = match self {
+            MegaUiDepthMode::None => return None,
+            MegaUiDepthMode::ReadOnly => false,
+            MegaUiDepthMode::ClearAndWrite => true,
+        };
+        Some(DepthStencilStateDescriptor {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilStateDescriptor {
+                front: StencilStateFaceDescriptor::IGNORE,
+                back: StencilStateFaceDescriptor::IGNORE,
+                read_mask: 0,
+                write_mask: 0,
+            },
+        })
+    }
+}
+
+/// An output slot of a render graph node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderGraphSlot {
+    /// Node name.
+    pub node: Cow<'static, str>,
+    /// Output slot name.
+    pub slot: Cow<'static, str>,
+}
+
+impl RenderGraphSlot {
+    /// Creates a slot from a node name and its output slot name.
+    pub fn new(node: impl Into<Cow<'static, str>>, slot: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            node: node.into(),
+            slot: slot.into(),
+        }
+    }
+}
+
+/// A resource that is used to store `bevy_megaui` contexts of secondary windows and named layers.
+/// Since [megaui::Ui] doesn't implement [Send] + [Sync], it's accessible only from
+/// thread-local systems.
+///
+/// The primary window context is stored in the [MegaUiContext] thread-local resource. Contexts
+/// for other windows are created as soon as the windows appear in the [bevy::window::Windows]
+/// resource.
+pub struct MegaUiContexts {
+    contexts: HashMap<WindowId, MegaUiContext>,
+    // Contexts of named layers, see `MegaUiSettings::layers`.
+    layers: HashMap<WindowId, HashMap<Cow<'static, str>, MegaUiContext>>,
+    // Is used to route mouse button and keyboard input, which isn't tied to any window.
+    active_window: WindowId,
+    // The layer of the active window that receives mouse button and keyboard input, `None`
+    // stands for the default context.
+    focused_layer: Option<Cow<'static, str>>,
+
+    cursor: EventReader<CursorMoved>,
+    received_character: EventReader<ReceivedCharacter>,
+}
+
+impl MegaUiContexts {
+    fn new() -> Self {
+        Self {
+            contexts: HashMap::new(),
+            layers: HashMap::new(),
+            active_window: WindowId::primary(),
+            focused_layer: None,
+            cursor: Default::default(),
+            received_character: Default::default(),
+        }
+    }
+
+    /// Returns a context of a secondary window, if it has been created.
+    /// The primary window context is the [MegaUiContext] resource.
+    pub fn get(&self, window_id: WindowId) -> Option<&MegaUiContext> {
+        self.contexts.get(&window_id)
+    }
+
+    /// Returns a context of a secondary window, if it has been created.
+    /// The primary window context is the [MegaUiContext] resource.
+    pub fn get_mut(&mut self, window_id: WindowId) -> Option<&mut MegaUiContext> {
+        self.contexts.get_mut(&window_id)
+    }
+
+    /// Iterates over contexts of secondary windows (not including named layers).
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (WindowId, &mut MegaUiContext)> {
+        self.contexts
+            .iter_mut()
+            .map(|(window_id, context)| (*window_id, context))
+    }
+
+    /// Returns a context of a named layer of the window, if it has been created,
+    /// see [MegaUiSettings::layers].
+    pub fn layer(&self, window_id: WindowId, name: &str) -> Option<&MegaUiContext> {
+        self.layers.get(&window_id)?.get(name)
+    }
+
+    /// Returns a context of a named layer of the window, if it has been created,
+    /// see [MegaUiSettings::layers].
+    pub fn layer_mut(&mut self, window_id: WindowId, name: &str) -> Option<&mut MegaUiContext> {
+        self.layers.get_mut(&window_id)?.get_mut(name)
+    }
+
+    // Returns the default context of a window, which is stored in a separate resource
+    // for the primary window.
+    fn context_mut<'a>(
+        &'a mut self,
+        primary_context: &'a mut MegaUiContext,
+        window_id: WindowId,
+    ) -> Option<&'a mut MegaUiContext> {
+        if window_id.is_primary() {
+            Some(primary_context)
+        } else {
+            self.contexts.get_mut(&window_id)
+        }
+    }
+
+    // Returns the default context and the layer contexts of a window ordered from the bottom
+    // to the top.
+    fn window_layers_mut<'a>(
+        &'a mut self,
+        primary_context: &'a mut MegaUiContext,
+        window_id: WindowId,
+        settings: &MegaUiSettings,
+    ) -> Vec<(Option<Cow<'static, str>>, &'a mut MegaUiContext)> {
+        let mut window_layers = Vec::new();
+        let MegaUiContexts {
+            contexts, layers, ..
+        } = self;
+        let context = if window_id.is_primary() {
+            Some(primary_context)
+        } else {
+            contexts.get_mut(&window_id)
+        };
+        if let Some(context) = context {
+            window_layers.push((None, context));
+        }
+        if let Some(layers) = layers.get_mut(&window_id) {
+            window_layers.extend(
+                layers
+                    .iter_mut()
+                    .map(|(name, context)| (Some(name.clone()), context)),
+            );
+        }
+        window_layers.sort_by_key(|(layer, _)| settings.layer_order(layer.as_deref()));
+        window_layers
+    }
+}
+
+/// A resource that is used to store the `bevy_megaui` context of the primary window.
 /// Since [megaui::Ui] doesn't implement [Send] + [Sync], it's accessible only from
 /// thread-local systems.
+///
+/// Contexts of secondary windows and named layers are stored in [MegaUiContexts].
 pub struct MegaUiContext {
     /// Megaui context.
     pub ui: megaui::Ui,
     ui_draw_lists: Vec<megaui::DrawList>,
-    font_texture: Handle<Texture>,
-    megaui_textures: HashMap<u32, Handle<Texture>>,
+    font_texture: MegaUiTexture,
+    megaui_textures: HashMap<u32, MegaUiTexture>,
+    // Is used for telling apart registrations of the same handle with different params.
+    texture_generation: u64,
+    // Rects of the windows drawn during the current frame.
+    window_rects: Vec<megaui::Rect>,
+    // Regions of megaui textures modified during the current frame.
+    dirty_texture_rects: HashMap<u32, Vec<megaui::Rect>>,
+    visible: bool,
 
     mouse_position: (f32, f32),
-    cursor: EventReader<CursorMoved>,
-    received_character: EventReader<ReceivedCharacter>,
 }
 
 impl MegaUiContext {
-    fn new(ui: megaui::Ui, font_texture: Handle<Texture>) -> Self {
+    fn new(texture_assets: &mut Assets<Texture>) -> Self {
+        let ui = megaui::Ui::new();
+        let font_texture = texture_assets.add(Texture::new(
+            Extent3d::new(ui.font_atlas.texture.width, ui.font_atlas.texture.height, 1),
+            TextureDimension::D2,
+            ui.font_atlas.texture.data.clone(),
+            TextureFormat::Rgba8Unorm,
+        ));
+
         Self {
             ui,
             ui_draw_lists: Vec::new(),
-            font_texture,
+            font_texture: MegaUiTexture {
+                handle: font_texture,
+                params: MegaUiTextureParams::default(),
+                generation: 0,
+            },
             megaui_textures: Default::default(),
+            texture_generation: 0,
+            window_rects: Vec::new(),
+            dirty_texture_rects: HashMap::new(),
+            visible: true,
             mouse_position: (0.0, 0.0),
-            cursor: Default::default(),
-            received_character: Default::default(),
         }
     }
 
     /// A helper function to draw a megaui window.
-    /// You may as well use [megaui::widgets::Window::new] if you prefer a builder pattern.
+    /// You may as well use [megaui::widgets::Window::new] if you prefer a builder pattern
+    /// (see [MegaUiContext::add_window_rect] in this case).
+    ///
+    /// The rect of an open window is recorded for render-side window effects.
     pub fn draw_window(
         &mut self,
         id: megaui::Id,
@@ -157,14 +1071,48 @@ impl MegaUiContext {
         params: impl Into<Option<WindowParams>>,
         f: impl FnOnce(&mut megaui::Ui),
     ) {
-        let params = params.into();
+        self.draw_window_closable(id, position, size, params, f);
+    }
+
+    /// Works the same way as [MegaUiContext::draw_window], but returns `false` if the window
+    /// has been closed with its close button (see [WindowParams::close_button]).
+    pub fn draw_window_closable(
+        &mut self,
+        id: megaui::Id,
+        position: Vector2,
+        size: Vector2,
+        params: impl Into<Option<WindowParams>>,
+        f: impl FnOnce(&mut megaui::Ui),
+    ) -> bool {
+        let window_rect = draw_megaui_window(&mut self.ui, id, position, size, params.into(), f);
+        if let Some(window_rect) = window_rect {
+            self.add_window_rect(window_rect);
+        }
+        window_rect.is_some()
+    }
+
+    /// Shows or hides the context, hidden contexts aren't drawn and don't receive input.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Returns whether the context is visible, see [MegaUiContext::set_visible].
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Records a window rect (in UI units) for render-side window effects, such as
+    /// [MegaUiSettings::background_blur]. Rects are kept until the end of the frame.
+    ///
+    /// Megaui uses the position passed to a window only when the window is created,
+    /// get the current rect with [active_window_rect] to follow windows dragged by the user.
+    pub fn add_window_rect(&mut self, rect: megaui::Rect) {
+        self.window_rects.push(rect);
+    }
 
-        megaui::widgets::Window::new(id, position, size)
-            .label(params.as_ref().map_or("", |params| &params.label))
-            .titlebar(params.as_ref().map_or(true, |params| params.titlebar))
-            .movable(params.as_ref().map_or(true, |params| params.movable))
-            .close_button(params.as_ref().map_or(false, |params| params.close_button))
-            .ui(&mut self.ui, f);
+    /// Returns the window rects recorded during the current frame.
+    pub fn window_rects(&self) -> &[megaui::Rect] {
+        &self.window_rects
     }
 
     /// Can accept either a strong or a weak handle.
@@ -175,28 +1123,173 @@ impl MegaUiContext {
     /// You'll want to pass a strong handle if a texture is used only in megaui and there's no
     /// handle copies stored anywhere else.
     pub fn set_megaui_texture(&mut self, id: u32, texture: Handle<Texture>) {
-        log::debug!("Set megaui texture: {:?}", texture);
-        self.megaui_textures.insert(id, texture);
+        self.set_megaui_texture_with_params(id, texture, MegaUiTextureParams::default());
+    }
+
+    /// Works the same way as [MegaUiContext::set_megaui_texture], but the texture is sampled with
+    /// the passed sampler instead of the one of the texture asset.
+    ///
+    /// This allows displaying a texture with nearest filtering in the UI, while it's filtered
+    /// linearly elsewhere.
+    pub fn set_megaui_texture_with_sampler(
+        &mut self,
+        id: u32,
+        texture: Handle<Texture>,
+        sampler: SamplerDescriptor,
+    ) {
+        self.set_megaui_texture_with_params(
+            id,
+            texture,
+            MegaUiTextureParams {
+                sampler: Some(sampler),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Registers a region of a texture atlas as a megaui texture.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of the atlas bounds.
+    pub fn set_megaui_texture_atlas_region(
+        &mut self,
+        id: u32,
+        texture_atlas: &TextureAtlas,
+        index: usize,
+    ) {
+        let rect = texture_atlas.textures[index];
+        self.set_megaui_texture_with_params(
+            id,
+            texture_atlas.texture.clone(),
+            MegaUiTextureParams {
+                region: Some(megaui::Rect::new(
+                    rect.min.x,
+                    rect.min.y,
+                    rect.max.x - rect.min.x,
+                    rect.max.y - rect.min.y,
+                )),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Works the same way as [MegaUiContext::set_megaui_texture], but allows to configure how
+    /// the texture is displayed.
+    pub fn set_megaui_texture_with_params(
+        &mut self,
+        id: u32,
+        texture: Handle<Texture>,
+        params: MegaUiTextureParams,
+    ) {
+        log::debug!("Set megaui texture: {:?} ({:?})", texture, params);
+        self.texture_generation += 1;
+        self.megaui_textures.insert(
+            id,
+            MegaUiTexture {
+                handle: texture,
+                params,
+                generation: self.texture_generation,
+            },
+        );
+    }
+
+    /// Marks a region (in pixels of the texture asset) of a megaui texture as modified.
+    ///
+    /// When the texture asset is modified during the same frame, only the marked regions are
+    /// uploaded instead of the whole texture. This doesn't apply to textures with
+    /// [MegaUiTextureParams::mipmaps] enabled. Marks are cleared at the end of each frame.
+    pub fn mark_megaui_texture_dirty(&mut self, id: u32, rect: megaui::Rect) {
+        self.dirty_texture_rects.entry(id).or_default().push(rect);
     }
 
     /// Removes a texture handle associated with the id.
     pub fn remove_megaui_texture(&mut self, id: u32) {
-        let texture_handle = self.megaui_textures.remove(&id);
-        log::debug!("Remove megaui texture: {:?}", texture_handle);
+        let texture = self.megaui_textures.remove(&id);
+        log::debug!(
+            "Remove megaui texture: {:?}",
+            texture.map(|texture| texture.handle)
+        );
+    }
+
+    // Returns the font texture for `None`, as megaui draw lists do.
+    fn megaui_texture(&self, id: Option<u32>) -> Option<&MegaUiTexture> {
+        match id {
+            Some(id) => self.megaui_textures.get(&id),
+            None => Some(&self.font_texture),
+        }
+    }
+
+    // Returns the regions of a megaui texture modified during the current frame, the font
+    // texture is always updated as a whole.
+    fn dirty_texture_rects(&self, id: Option<u32>) -> &[megaui::Rect] {
+        match id.and_then(|id| self.dirty_texture_rects.get(&id)) {
+            Some(dirty_texture_rects) => dirty_texture_rects,
+            None => &[],
+        }
     }
 
     // Is called when we get an event that a texture asset is removed.
     fn remove_texture(&mut self, texture_handle: &Handle<Texture>) {
         log::debug!("Removing megaui handles: {:?}", texture_handle);
-        self.megaui_textures = self
-            .megaui_textures
-            .iter()
-            .map(|(id, texture)| (*id, texture.clone()))
-            .filter(|(_, texture)| texture != texture_handle)
-            .collect();
+        self.megaui_textures
+            .retain(|_, texture| texture.handle != *texture_handle);
+    }
+}
+
+/// Params that define how a texture is displayed, see
+/// [MegaUiContext::set_megaui_texture_with_params].
+#[derive(Debug, Clone, Default)]
+pub struct MegaUiTextureParams {
+    /// A sampler overriding the texture asset one (`None` by default).
+    pub sampler: Option<SamplerDescriptor>,
+    /// A region of the texture in pixels that is displayed instead of the whole texture
+    /// (`None` by default).
+    pub region: Option<megaui::Rect>,
+    /// Defines how `R8Unorm` and `Rg8Unorm` textures are displayed
+    /// ([MegaUiTextureSwizzle::Color] by default).
+    pub swizzle: MegaUiTextureSwizzle,
+    /// A layer of a texture array to display (`0` by default).
+    pub layer: u32,
+    /// Generates a mip chain when a texture is uploaded, which avoids aliasing of textures
+    /// displayed smaller than their size (`false` by default).
+    ///
+    /// Unless `sampler` is set, the texture asset sampler is used with linear mipmap filtering.
+    /// Mipmaps are generated for 8-bit formats only, colors are filtered in linear space and weighted
+    /// by alpha.
+    pub mipmaps: bool,
+    /// Defines whether the texture has premultiplied alpha (`false` by default).
+    ///
+    /// Textures are premultiplied or unpremultiplied on upload if it doesn't match
+    /// [MegaUiPipelineConfig::blend_mode]. Only 8-bit formats are converted.
+    pub premultiplied: bool,
+}
+
+/// Defines how textures with less than 4 channels are mapped to RGBA.
+///
+/// Textures of `Rgba8Unorm`, `Bgra8Unorm`, `Rgba16Float` formats and their sRGB variants are
+/// displayed as is, other formats aren't supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MegaUiTextureSwizzle {
+    /// `R` is displayed as grayscale (`RRR1`), `RG` is mapped to `RG01`.
+    Color,
+    /// `R` is displayed as an alpha mask (`111R`), `RG` is treated as luminance-alpha (`RRRG`).
+    AlphaMask,
+}
+
+impl Default for MegaUiTextureSwizzle {
+    fn default() -> Self {
+        MegaUiTextureSwizzle::Color
     }
 }
 
+#[derive(Debug, Clone)]
+struct MegaUiTexture {
+    handle: Handle<Texture>,
+    params: MegaUiTextureParams,
+    generation: u64,
+}
+
 /// Params that are used for defining a window with [MegaUiContext::draw_window].
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct WindowParams {
@@ -221,6 +1314,15 @@ impl Default for WindowParams {
     }
 }
 
+#[derive(Debug, Default)]
+struct WindowSizes(HashMap<WindowId, WindowSize>);
+
+impl WindowSizes {
+    fn get(&self, window_id: WindowId) -> WindowSize {
+        self.0.get(&window_id).cloned().unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq)]
 struct WindowSize {
     physical_width: f32,
@@ -246,6 +1348,51 @@ impl WindowSize {
     fn height(&self) -> f32 {
         self.physical_height / self.scale_factor
     }
+
+    // Returns the size of the texture the UI is rendered to, see `MegaUiSettings::resolution_scale`.
+    fn render_target_size(&self, resolution_scale: f32) -> (u32, u32) {
+        let resolution_scale = resolution_scale.min(1.0);
+        (
+            ((self.physical_width * resolution_scale).round() as u32).max(1),
+            ((self.physical_height * resolution_scale).round() as u32).max(1),
+        )
+    }
+}
+
+/// Returns the rect of the window that is being drawn, it has to be called inside a window.
+///
+/// Unlike the position passed to [megaui::widgets::Window::new], the rect follows
+/// the window when the user drags it.
+pub fn active_window_rect(ui: &mut megaui::Ui) -> megaui::Rect {
+    let window = ui.get_active_window_context().window;
+    megaui::Rect::new(
+        window.position.x,
+        window.position.y,
+        window.size.x,
+        window.size.y,
+    )
+}
+
+// Draws a window, returning its current rect if the window hasn't been closed.
+fn draw_megaui_window(
+    ui: &mut megaui::Ui,
+    id: megaui::Id,
+    position: Vector2,
+    size: Vector2,
+    params: Option<WindowParams>,
+    f: impl FnOnce(&mut megaui::Ui),
+) -> Option<megaui::Rect> {
+    let mut window_rect = None;
+    let opened = megaui::widgets::Window::new(id, position, size)
+        .label(params.as_ref().map_or("", |params| &params.label))
+        .titlebar(params.as_ref().map_or(true, |params| params.titlebar))
+        .movable(params.as_ref().map_or(true, |params| params.movable))
+        .close_button(params.as_ref().map_or(false, |params| params.close_button))
+        .ui(ui, |ui| {
+            window_rect = Some(active_window_rect(ui));
+            f(ui);
+        });
+    window_rect.filter(|_| opened)
 }
 
 impl MegaUiContext {
@@ -256,32 +1403,80 @@ impl MegaUiContext {
 }
 
 /// The names of `bevy_megaui` nodes.
+///
+/// The nodes of secondary windows are named with [window_node_name].
 pub mod node {
+    use bevy::window::WindowId;
+    use std::borrow::Cow;
+
     /// The main megaui pass.
     pub const MEGAUI_PASS: &str = "megaui_pass";
     /// Keeps the transform uniform up to date.
     pub const MEGAUI_TRANSFORM: &str = "megaui_transform";
+    /// A swap chain node that is created for secondary windows.
+    pub const MEGAUI_SWAP_CHAIN: &str = "megaui_swap_chain";
+    /// A depth texture node that is created for secondary windows, and for the primary one
+    /// if the default depth source is cleared (see [crate::MegaUiRenderGraphConfig::depth_source]).
+    pub const MEGAUI_DEPTH_TEXTURE: &str = "megaui_depth_texture";
+    /// A sampled color attachment node that is created for secondary windows.
+    pub const MEGAUI_SAMPLED_COLOR_ATTACHMENT: &str = "megaui_sampled_color_attachment";
+
+    /// Returns the name of a node for the window.
+    /// The primary window nodes use the plain names, other window nodes are suffixed with their id.
+    pub fn window_node_name(name: &'static str, window_id: WindowId) -> Cow<'static, str> {
+        if window_id.is_primary() {
+            Cow::Borrowed(name)
+        } else {
+            Cow::Owned(format!("{}_{}", name, window_id))
+        }
+    }
+}
+
+// The uniform buffers of each window are stored in `RenderResourceBindings` under their own names.
+fn window_resource_binding_name(
+    binding_name: &'static str,
+    window_id: WindowId,
+) -> Cow<'static, str> {
+    node::window_node_name(binding_name, window_id)
 }
 
 impl Plugin for MegaUiPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_system_to_stage(stage::PRE_UPDATE, process_input.system());
+        app.add_system_to_stage(stage::POST_UPDATE, setup_window_pipelines.system());
+        app.add_system_to_stage(stage::POST_UPDATE, connect_sampled_color_source.system());
 
         let resources = app.resources_mut();
 
-        let ui = megaui::Ui::new();
-        let font_texture = {
+        let primary_context = {
             let mut assets = resources.get_mut::<Assets<Texture>>().unwrap();
-            assets.add(Texture::new(
-                Extent3d::new(ui.font_atlas.texture.width, ui.font_atlas.texture.height, 1),
-                TextureDimension::D2,
-                ui.font_atlas.texture.data.clone(),
-                TextureFormat::Rgba8Unorm,
-            ))
+            MegaUiContext::new(&mut assets)
         };
+
         resources.get_or_insert_with(MegaUiSettings::default);
-        resources.insert(WindowSize::new(0.0, 0.0, 0.0));
-        resources.insert_thread_local(MegaUiContext::new(ui, font_texture.clone()));
+        let render_graph_config = resources
+            .get_or_insert_with(MegaUiRenderGraphConfig::default)
+            .clone();
+        let pipeline_config = resources
+            .get_or_insert_with(MegaUiPipelineConfig::default)
+            .clone();
+        resources.insert(WindowSizes::default());
+        resources.insert(MegaUiRenderStats::default());
+        if let Some(shader_paths) = &pipeline_config.hot_reload_shaders {
+            let hot_reloaded_shaders = {
+                let asset_server = resources.get::<AssetServer>().unwrap();
+                if let Err(err) = asset_server.watch_for_changes() {
+                    log::error!("Failed to watch megaui shaders for changes: {:?}", err);
+                }
+                HotReloadedShaders {
+                    vertex: asset_server.load(shader_paths.vertex.as_str()),
+                    fragment: asset_server.load(shader_paths.fragment.as_str()),
+                }
+            };
+            resources.insert(hot_reloaded_shaders);
+        }
+        resources.insert_thread_local(primary_context);
+        resources.insert_thread_local(MegaUiContexts::new());
 
         let mut pipelines = resources.get_mut::<Assets<PipelineDescriptor>>().unwrap();
         let mut shaders = resources.get_mut::<Assets<Shader>>().unwrap();
@@ -289,57 +1484,399 @@ impl Plugin for MegaUiPlugin {
 
         pipelines.set_untracked(
             MEGAUI_PIPELINE_HANDLE,
-            build_megaui_pipeline(&mut shaders, msaa.samples),
+            build_megaui_pipeline(
+                &mut shaders,
+                &pipeline_config,
+                msaa.samples,
+                render_graph_config.depth_mode,
+            ),
+        );
+        pipelines.set_untracked(
+            MEGAUI_BLUR_PIPELINE_HANDLE,
+            build_megaui_blur_pipeline(&mut shaders, render_graph_config.depth_mode),
+        );
+        pipelines.set_untracked(
+            MEGAUI_SHADOW_PIPELINE_HANDLE,
+            build_megaui_shadow_pipeline(&mut shaders, render_graph_config.depth_mode),
+        );
+        pipelines.set_untracked(
+            MEGAUI_COMPOSITE_PIPELINE_HANDLE,
+            build_megaui_composite_pipeline(&mut shaders, pipeline_config.blend_mode),
         );
         let mut render_graph = resources.get_mut::<RenderGraph>().unwrap();
 
-        render_graph.add_node(node::MEGAUI_PASS, MegaUiNode::new(&msaa, font_texture));
-        render_graph
-            .add_node_edge(base::node::MAIN_PASS, node::MEGAUI_PASS)
-            .unwrap();
+        setup_primary_window_pipeline(&mut render_graph, &msaa, &render_graph_config);
+    }
+}
 
-        render_graph
-            .add_slot_edge(
-                base::node::PRIMARY_SWAP_CHAIN,
-                WindowSwapChainNode::OUT_TEXTURE,
-                node::MEGAUI_PASS,
-                if msaa.samples > 1 {
-                    "color_resolve_target"
-                } else {
-                    "color_attachment"
-                },
-            )
-            .unwrap();
+fn setup_primary_window_pipeline(
+    render_graph: &mut RenderGraph,
+    msaa: &Msaa,
+    config: &MegaUiRenderGraphConfig,
+) {
+    let window_id = WindowId::primary();
+
+    for dependency in &config.dependencies {
+        expect_node(render_graph, dependency, "a megaui pass dependency");
+    }
+    expect_node(render_graph, &config.color_source.node, "a color source");
+
+    // The base render graph depth texture doesn't follow `Msaa`, so the megaui pass gets its own
+    // one if the scene depth isn't needed.
+    let default_config = MegaUiRenderGraphConfig::default();
+    let owned_depth_texture = config.depth_source == default_config.depth_source
+        && config.depth_mode == MegaUiDepthMode::ClearAndWrite;
 
+    let depth_operations = config.depth_mode.depth_operations(true);
+    let depth_source = if owned_depth_texture {
+        add_depth_texture_node(render_graph, msaa, window_id)
+    } else {
+        if depth_operations.is_some() {
+            expect_node(render_graph, &config.depth_source.node, "a depth source");
+        }
+        config.depth_source.clone()
+    };
+    // The sampled color source gets reconnected if it's re-created on changing `Msaa`,
+    // see `connect_sampled_color_source`.
+    let sampled_color_source = if msaa.samples > 1 {
+        expect_node(
+            render_graph,
+            &config.sampled_color_source.node,
+            "a sampled color source",
+        );
+        Some(config.sampled_color_source.clone())
+    } else {
+        None
+    };
+
+    let attachments = MegaUiPassAttachments {
+        depth_operations,
+        color_source_is_swap_chain: render_graph
+            .get_node::<WindowSwapChainNode>(config.color_source.node.to_string())
+            .is_ok(),
+        clear_color_source: false,
+        owned_sampled_color_attachment: false,
+        fixed_depth_sample_count: Some(msaa.samples).filter(|_| {
+            depth_operations.is_some() && config.depth_source == default_config.depth_source
+        }),
+    };
+    render_graph.add_node(
+        node::MEGAUI_PASS,
+        MegaUiNode::new(window_id, msaa, attachments),
+    );
+    for dependency in &config.dependencies {
         render_graph
-            .add_slot_edge(
-                base::node::MAIN_DEPTH_TEXTURE,
-                WindowTextureNode::OUT_TEXTURE,
-                node::MEGAUI_PASS,
-                "depth",
-            )
+            .add_node_edge(dependency.to_string(), node::MEGAUI_PASS)
             .unwrap();
+    }
+
+    connect_window_textures(
+        render_graph,
+        node::MEGAUI_PASS,
+        &config.color_source,
+        depth_operations.map(|_| &depth_source),
+        sampled_color_source.as_ref(),
+    );
+
+    // Transform.
+    render_graph.add_system_node(node::MEGAUI_TRANSFORM, MegaUiTransformNode::new(window_id));
+    render_graph
+        .add_node_edge(node::MEGAUI_TRANSFORM, node::MEGAUI_PASS)
+        .unwrap();
+}
+
+fn expect_node(render_graph: &RenderGraph, name: &str, purpose: &str) {
+    if render_graph.get_node_id(name.to_string()).is_err() {
+        panic!(
+            "Render graph node `{}` configured as {} in `MegaUiRenderGraphConfig` doesn't exist \
+             (make sure that the plugin adding the node is added before `MegaUiPlugin`)",
+            name, purpose
+        );
+    }
+}
 
-        if msaa.samples > 1 {
+// Secondary windows don't have any nodes in the base render graph, so we create the attachment
+// nodes ourselves (they follow the `Msaa` sample count), and the swap chain node as well, unless
+// the window is rendered by the app (see `MegaUiRenderGraphConfig::secondary_windows`).
+fn setup_secondary_window_pipeline(
+    render_graph: &mut RenderGraph,
+    msaa: &Msaa,
+    config: &MegaUiRenderGraphConfig,
+    window_id: WindowId,
+) {
+    let megaui_pass = node::window_node_name(node::MEGAUI_PASS, window_id);
+    let megaui_transform = node::window_node_name(node::MEGAUI_TRANSFORM, window_id);
+
+    let depth_operations = config.depth_mode.depth_operations(false);
+
+    let window_config = config.secondary_windows.get(&window_id);
+    let color_source = match window_config {
+        Some(window_config) => {
+            for dependency in &window_config.dependencies {
+                expect_node(render_graph, dependency, "a megaui pass dependency");
+            }
+            expect_node(
+                render_graph,
+                &window_config.color_source.node,
+                "a color source",
+            );
+            window_config.color_source.clone()
+        }
+        None => {
+            let swap_chain = node::window_node_name(node::MEGAUI_SWAP_CHAIN, window_id);
+            render_graph.add_node(swap_chain.clone(), WindowSwapChainNode::new(window_id));
+            RenderGraphSlot::new(swap_chain, WindowSwapChainNode::OUT_TEXTURE)
+        }
+    };
+    let depth_source =
+        depth_operations.map(|_| add_depth_texture_node(render_graph, msaa, window_id));
+    let sampled_color_source = add_sampled_color_attachment_node(render_graph, msaa, window_id);
+
+    let attachments = MegaUiPassAttachments {
+        depth_operations,
+        color_source_is_swap_chain: render_graph
+            .get_node::<WindowSwapChainNode>(color_source.node.to_string())
+            .is_ok(),
+        clear_color_source: window_config.is_none(),
+        owned_sampled_color_attachment: true,
+        fixed_depth_sample_count: None,
+    };
+    render_graph.add_node(
+        megaui_pass.clone(),
+        MegaUiNode::new(window_id, msaa, attachments),
+    );
+    if let Some(window_config) = window_config {
+        for dependency in &window_config.dependencies {
             render_graph
-                .add_slot_edge(
-                    base::node::MAIN_SAMPLED_COLOR_ATTACHMENT,
-                    WindowSwapChainNode::OUT_TEXTURE,
-                    node::MEGAUI_PASS,
-                    "color_attachment",
-                )
+                .add_node_edge(dependency.to_string(), megaui_pass.to_string())
                 .unwrap();
         }
+    }
+    connect_window_textures(
+        render_graph,
+        &megaui_pass,
+        &color_source,
+        depth_source.as_ref(),
+        Some(&sampled_color_source),
+    );
 
-        // Transform.
-        render_graph.add_system_node(node::MEGAUI_TRANSFORM, MegaUiTransformNode::new());
-        render_graph
-            .add_node_edge(node::MEGAUI_TRANSFORM, node::MEGAUI_PASS)
-            .unwrap();
+    render_graph.add_system_node(
+        megaui_transform.clone(),
+        MegaUiTransformNode::new(window_id),
+    );
+    render_graph
+        .add_node_edge(megaui_transform.into_owned(), megaui_pass.into_owned())
+        .unwrap();
+}
+
+// Adds a depth texture node that follows the `Msaa` sample count.
+fn add_depth_texture_node(
+    render_graph: &mut RenderGraph,
+    msaa: &Msaa,
+    window_id: WindowId,
+) -> RenderGraphSlot {
+    let name = node::window_node_name(node::MEGAUI_DEPTH_TEXTURE, window_id);
+    render_graph.add_node(
+        name.clone(),
+        MegaUiWindowTextureNode::new(
+            window_id,
+            TextureDescriptor {
+                size: Extent3d::new(1, 1, 1),
+                mip_level_count: 1,
+                sample_count: msaa.samples,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Depth32Float,
+                usage: TextureUsage::OUTPUT_ATTACHMENT,
+            },
+        ),
+    );
+    RenderGraphSlot::new(name, MegaUiWindowTextureNode::OUT_TEXTURE)
+}
+
+// Adds a sampled color attachment node that follows the `Msaa` sample count.
+fn add_sampled_color_attachment_node(
+    render_graph: &mut RenderGraph,
+    msaa: &Msaa,
+    window_id: WindowId,
+) -> RenderGraphSlot {
+    let name = node::window_node_name(node::MEGAUI_SAMPLED_COLOR_ATTACHMENT, window_id);
+    render_graph.add_node(
+        name.clone(),
+        MegaUiWindowTextureNode::multisampled(
+            window_id,
+            TextureDescriptor {
+                size: Extent3d::new(1, 1, 1),
+                mip_level_count: 1,
+                sample_count: msaa.samples,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::default(),
+                usage: TextureUsage::OUTPUT_ATTACHMENT,
+            },
+        ),
+    );
+    RenderGraphSlot::new(name, MegaUiWindowTextureNode::OUT_TEXTURE)
+}
+
+fn connect_window_textures(
+    render_graph: &mut RenderGraph,
+    megaui_pass: &str,
+    color_source: &RenderGraphSlot,
+    depth_source: Option<&RenderGraphSlot>,
+    sampled_color_source: Option<&RenderGraphSlot>,
+) {
+    connect_slot(
+        render_graph,
+        color_source,
+        megaui_pass,
+        MegaUiNode::IN_COLOR_ATTACHMENT,
+    );
+    if let Some(depth_source) = depth_source {
+        connect_slot(
+            render_graph,
+            depth_source,
+            megaui_pass,
+            MegaUiNode::IN_DEPTH,
+        );
+    }
+    if let Some(sampled_color_source) = sampled_color_source {
+        connect_slot(
+            render_graph,
+            sampled_color_source,
+            megaui_pass,
+            MegaUiNode::IN_SAMPLED_COLOR_ATTACHMENT,
+        );
+    }
+}
+
+fn connect_slot(
+    render_graph: &mut RenderGraph,
+    source: &RenderGraphSlot,
+    node: &str,
+    input: &'static str,
+) {
+    render_graph
+        .add_slot_edge(
+            source.node.to_string(),
+            source.slot.to_string(),
+            node.to_string(),
+            input,
+        )
+        .unwrap();
+}
+
+fn is_input_connected(render_graph: &RenderGraph, node: &str, input: &'static str) -> bool {
+    render_graph
+        .get_node_state(node.to_string())
+        .map_or(false, |node_state| {
+            node_state
+                .input_slots
+                .get_slot_index(input)
+                .map_or(false, |index| {
+                    node_state.edges.get_input_slot_edge(index).is_ok()
+                })
+        })
+}
+
+// The primary window megaui pass gets connected to the sampled color source only if MSAA
+// is enabled, so we connect it once MSAA gets enabled at runtime. Removing the source node
+// (to re-create it with a new sample count) removes its edges as well, so the megaui pass
+// gets reconnected once the node is added back.
+fn connect_sampled_color_source(
+    mut prev_samples: Local<u32>,
+    msaa: Res<Msaa>,
+    render_graph_config: Res<MegaUiRenderGraphConfig>,
+    mut render_graph: ResMut<RenderGraph>,
+) {
+    let msaa_changed = *prev_samples != msaa.samples;
+    *prev_samples = msaa.samples;
+
+    if msaa.samples == 1
+        || is_input_connected(
+            &render_graph,
+            node::MEGAUI_PASS,
+            MegaUiNode::IN_SAMPLED_COLOR_ATTACHMENT,
+        )
+    {
+        return;
+    }
+
+    let sampled_color_source = &render_graph_config.sampled_color_source;
+    if render_graph
+        .get_node_id(sampled_color_source.node.to_string())
+        .is_err()
+    {
+        // The node can be added in the next frames, so the error is logged once per change.
+        if msaa_changed {
+            log::error!(
+                "The megaui pass isn't rendered with MSAA enabled: sampled color source node \
+                 `{}` doesn't exist",
+                sampled_color_source.node
+            );
+        }
+        return;
+    }
+    connect_slot(
+        &mut render_graph,
+        sampled_color_source,
+        node::MEGAUI_PASS,
+        MegaUiNode::IN_SAMPLED_COLOR_ATTACHMENT,
+    );
+}
+
+fn setup_window_pipelines(
+    mut window_created_reader: Local<EventReader<WindowCreated>>,
+    window_created_events: Res<Events<WindowCreated>>,
+    msaa: Res<Msaa>,
+    render_graph_config: Res<MegaUiRenderGraphConfig>,
+    mut render_graph: ResMut<RenderGraph>,
+) {
+    for window_created in window_created_reader.iter(&window_created_events) {
+        // The primary window pipeline is set up on building the plugin.
+        if window_created.id.is_primary() {
+            continue;
+        }
+        log::debug!("Setting up a megaui pipeline: {:?}", window_created.id);
+        setup_secondary_window_pipeline(
+            &mut render_graph,
+            &msaa,
+            &render_graph_config,
+            window_created.id,
+        );
     }
 }
 
-fn build_megaui_pipeline(shaders: &mut Assets<Shader>, sample_count: u32) -> PipelineDescriptor {
+fn build_megaui_pipeline(
+    shaders: &mut Assets<Shader>,
+    config: &MegaUiPipelineConfig,
+    sample_count: u32,
+    depth_mode: MegaUiDepthMode,
+) -> PipelineDescriptor {
+    if let Some(pipeline_descriptor) = &config.pipeline_descriptor {
+        return pipeline_descriptor.clone();
+    }
+
+    let vertex_shader = config.vertex_shader.clone().unwrap_or_else(|| {
+        shaders.add(Shader::from_glsl(
+            ShaderStage::Vertex,
+            if cfg!(target_arch = "wasm32") {
+                include_str!("megaui.es.vert")
+            } else {
+                include_str!("megaui.vert")
+            },
+        ))
+    });
+    let fragment_shader = config.fragment_shader.clone().unwrap_or_else(|| {
+        shaders.add(Shader::from_glsl(
+            ShaderStage::Fragment,
+            if cfg!(target_arch = "wasm32") {
+                include_str!("megaui.es.frag")
+            } else {
+                include_str!("megaui.frag")
+            },
+        ))
+    });
+
+    let (color_blend, alpha_blend) = config.blend_mode.blend_descriptors();
     PipelineDescriptor {
         rasterization_state: Some(RasterizationStateDescriptor {
             front_face: FrontFace::Cw,
@@ -349,50 +1886,186 @@ fn build_megaui_pipeline(shaders: &mut Assets<Shader>, sample_count: u32) -> Pip
             depth_bias_clamp: 0.0,
             clamp_depth: false,
         }),
-        depth_stencil_state: Some(DepthStencilStateDescriptor {
-            format: TextureFormat::Depth32Float,
-            depth_write_enabled: true,
-            depth_compare: CompareFunction::LessEqual,
-            stencil: StencilStateDescriptor {
-                front: StencilStateFaceDescriptor::IGNORE,
-                back: StencilStateFaceDescriptor::IGNORE,
-                read_mask: 0,
-                write_mask: 0,
-            },
-        }),
+        depth_stencil_state: depth_mode.depth_stencil_state(),
         color_states: vec![ColorStateDescriptor {
             format: TextureFormat::default(),
-            color_blend: BlendDescriptor {
-                src_factor: BlendFactor::SrcAlpha,
-                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                operation: BlendOperation::Add,
-            },
-            alpha_blend: BlendDescriptor {
-                src_factor: BlendFactor::OneMinusDstAlpha,
-                dst_factor: BlendFactor::One,
-                operation: BlendOperation::Add,
-            },
+            color_blend,
+            alpha_blend,
             write_mask: ColorWrite::ALL,
         }],
         index_format: IndexFormat::Uint16,
         sample_count,
         ..PipelineDescriptor::new(ShaderStages {
-            vertex: shaders.add(Shader::from_glsl(
-                ShaderStage::Vertex,
-                if cfg!(target_arch = "wasm32") {
-                    include_str!("megaui.es.vert")
-                } else {
-                    include_str!("megaui.vert")
-                },
-            )),
-            fragment: Some(shaders.add(Shader::from_glsl(
-                ShaderStage::Fragment,
-                if cfg!(target_arch = "wasm32") {
-                    include_str!("megaui.es.frag")
-                } else {
-                    include_str!("megaui.frag")
-                },
-            ))),
+            vertex: vertex_shader,
+            fragment: Some(fragment_shader),
+        })
+    }
+}
+
+fn build_megaui_composite_pipeline(
+    shaders: &mut Assets<Shader>,
+    blend_mode: MegaUiBlendMode,
+) -> PipelineDescriptor {
+    let vertex_shader = shaders.add(Shader::from_glsl(
+        ShaderStage::Vertex,
+        if cfg!(target_arch = "wasm32") {
+            include_str!("megaui_composite.es.vert")
+        } else {
+            include_str!("megaui_composite.vert")
+        },
+    ));
+    let fragment_shader = shaders.add(Shader::from_glsl(
+        ShaderStage::Fragment,
+        if cfg!(target_arch = "wasm32") {
+            include_str!("megaui_composite.es.frag")
+        } else {
+            include_str!("megaui_composite.frag")
+        },
+    ));
+
+    let (color_blend, alpha_blend) = blend_mode.composite_blend_descriptors();
+    PipelineDescriptor {
+        rasterization_state: Some(RasterizationStateDescriptor {
+            front_face: FrontFace::Cw,
+            cull_mode: CullMode::None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+            clamp_depth: false,
+        }),
+        depth_stencil_state: None,
+        color_states: vec![ColorStateDescriptor {
+            format: TextureFormat::default(),
+            color_blend,
+            alpha_blend,
+            write_mask: ColorWrite::ALL,
+        }],
+        ..PipelineDescriptor::new(ShaderStages {
+            vertex: vertex_shader,
+            fragment: Some(fragment_shader),
+        })
+    }
+}
+
+fn build_megaui_blur_pipeline(
+    shaders: &mut Assets<Shader>,
+    depth_mode: MegaUiDepthMode,
+) -> PipelineDescriptor {
+    let vertex_shader = shaders.add(Shader::from_glsl(
+        ShaderStage::Vertex,
+        if cfg!(target_arch = "wasm32") {
+            include_str!("megaui_blur.es.vert")
+        } else {
+            include_str!("megaui_blur.vert")
+        },
+    ));
+    let fragment_shader = shaders.add(Shader::from_glsl(
+        ShaderStage::Fragment,
+        if cfg!(target_arch = "wasm32") {
+            include_str!("megaui_blur.es.frag")
+        } else {
+            include_str!("megaui_blur.frag")
+        },
+    ));
+
+    // Backgrounds are opaque, so they are blended as premultiplied regardless of the blend mode.
+    let (color_blend, alpha_blend) = MegaUiBlendMode::Premultiplied.blend_descriptors();
+    PipelineDescriptor {
+        rasterization_state: Some(RasterizationStateDescriptor {
+            front_face: FrontFace::Cw,
+            cull_mode: CullMode::None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+            clamp_depth: false,
+        }),
+        depth_stencil_state: depth_mode.depth_stencil_state(),
+        color_states: vec![ColorStateDescriptor {
+            format: TextureFormat::default(),
+            color_blend,
+            alpha_blend,
+            write_mask: ColorWrite::ALL,
+        }],
+        index_format: IndexFormat::Uint16,
+        ..PipelineDescriptor::new(ShaderStages {
+            vertex: vertex_shader,
+            fragment: Some(fragment_shader),
+        })
+    }
+}
+
+fn build_megaui_shadow_pipeline(
+    shaders: &mut Assets<Shader>,
+    depth_mode: MegaUiDepthMode,
+) -> PipelineDescriptor {
+    let vertex_shader = shaders.add(Shader::from_glsl(
+        ShaderStage::Vertex,
+        if cfg!(target_arch = "wasm32") {
+            include_str!("megaui_decoration.es.vert")
+        } else {
+            include_str!("megaui_decoration.vert")
+        },
+    ));
+    let fragment_shader = shaders.add(Shader::from_glsl(
+        ShaderStage::Fragment,
+        if cfg!(target_arch = "wasm32") {
+            include_str!("megaui_decoration.es.frag")
+        } else {
+            include_str!("megaui_decoration.frag")
+        },
+    ));
+
+    let (color_blend, alpha_blend) = MegaUiBlendMode::Premultiplied.blend_descriptors();
+    PipelineDescriptor {
+        rasterization_state: Some(RasterizationStateDescriptor {
+            front_face: FrontFace::Cw,
+            cull_mode: CullMode::None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+            clamp_depth: false,
+        }),
+        depth_stencil_state: depth_mode.depth_stencil_state(),
+        color_states: vec![ColorStateDescriptor {
+            format: TextureFormat::default(),
+            color_blend,
+            alpha_blend,
+            write_mask: ColorWrite::ALL,
+        }],
+        // Shadows of many windows can exceed 16-bit indices.
+        index_format: IndexFormat::Uint32,
+        ..PipelineDescriptor::new(ShaderStages {
+            vertex: vertex_shader,
+            fragment: Some(fragment_shader),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::draw_frame;
+    use megaui::InputHandler;
+
+    #[test]
+    fn test_dragged_window_rect() {
+        let mut ui = megaui::Ui::new();
+        assert_eq!(
+            draw_frame(&mut ui),
+            Some(megaui::Rect::new(10.0, 10.0, 100.0, 50.0))
+        );
+
+        // Drags the window by its titlebar.
+        ui.mouse_move((20.0, 15.0));
+        ui.mouse_down((20.0, 15.0));
+        draw_frame(&mut ui);
+        ui.mouse_move((60.0, 45.0));
+        draw_frame(&mut ui);
+        ui.mouse_up((60.0, 45.0));
+
+        assert_eq!(
+            draw_frame(&mut ui),
+            Some(megaui::Rect::new(50.0, 40.0, 100.0, 50.0))
+        );
+    }
+}
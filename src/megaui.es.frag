@@ -4,9 +4,16 @@ precision mediump float;
 
 in vec2 v_Uv;
 in vec4 v_Color;
+in vec2 v_WindowLocal;
+in vec3 v_WindowShape;
 
 out vec4 o_Target;
 
+layout(std140) uniform MegaUiColorTransform {
+    vec4 ui_color_scale;
+    mat4 ui_color_matrix;
+};
+
 uniform sampler2D MegaUiTexture_texture;
 
 vec4 encodeSRGB(vec4 linearRGB_in) {
@@ -17,6 +24,30 @@ vec4 encodeSRGB(vec4 linearRGB_in) {
     return vec4(mix(a, b, c), linearRGB_in.a);
 }
 
+// The color matrix is affine, its last column is an offset. The offset and the output range are
+// scaled by alpha of premultiplied colors, which is indicated by the last component of the column.
+vec3 filterColor(vec4 color) {
+    float alphaScale = mix(1.0, color.a, ui_color_matrix[3].w);
+    vec3 filtered = mat3(ui_color_matrix) * color.rgb + ui_color_matrix[3].rgb * alphaScale;
+    return clamp(filtered, 0.0, alphaScale);
+}
+
+// Cuts the rounded corners of the window the fragment belongs to. Only the corner squares are
+// affected, so the geometry outside of the window rect isn't clipped.
+float cornerCoverage(vec2 local, vec3 shape) {
+    float radius = shape.z;
+    vec2 q = abs(local) - shape.xy + radius;
+    float distance = length(max(q, 0.0)) - radius;
+    // The arc is smoothed over a pixel outside the window, as its straight edges aren't smoothed.
+    float pixel = fwidth(distance);
+    float coverage = 1.0 - smoothstep(-pixel, pixel, distance - pixel);
+    return (radius > 0.0 && q.x > 0.0 && q.y > 0.0) ? coverage : 1.0;
+}
+
 void main() {
-    o_Target = encodeSRGB(v_Color * texture(MegaUiTexture_texture, v_Uv));
+    vec4 color = v_Color * ui_color_scale * texture(MegaUiTexture_texture, v_Uv);
+    // Premultiplied colors are scaled as a whole.
+    float coverage = cornerCoverage(v_WindowLocal, v_WindowShape);
+    color *= mix(vec4(1.0, 1.0, 1.0, coverage), vec4(coverage), ui_color_matrix[3].w);
+    o_Target = encodeSRGB(vec4(filterColor(color), color.a));
 }
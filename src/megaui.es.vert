@@ -3,17 +3,32 @@
 in vec3 Vertex_Position;
 in vec2 Vertex_Uv;
 in vec4 Vertex_Color;
+in vec4 Vertex_WindowRect;
+in float Vertex_CornerRadius;
 
 out vec2 v_Uv;
 out vec4 v_Color;
+// Position relative to the window center.
+out vec2 v_WindowLocal;
+// Half size of the window and corner radius.
+out vec3 v_WindowShape;
 
 layout(std140) uniform MegaUiTransform {
     vec2 scale;
     vec2 translation;
 };
 
+layout(std140) uniform MegaUiAffineTransform {
+    vec2 ui_x_axis;
+    vec2 ui_y_axis;
+    vec2 ui_translation;
+};
+
 void main() {
     v_Uv = Vertex_Uv;
     v_Color = Vertex_Color;
-    gl_Position = vec4(Vertex_Position * vec3(scale, 1.0) + vec3(translation, 0.0), 1.0);
+    v_WindowLocal = Vertex_Position.xy - Vertex_WindowRect.xy;
+    v_WindowShape = vec3(Vertex_WindowRect.zw, Vertex_CornerRadius);
+    vec2 position = ui_x_axis * Vertex_Position.x + ui_y_axis * Vertex_Position.y + ui_translation;
+    gl_Position = vec4(position * scale + translation, Vertex_Position.z, 1.0);
 }
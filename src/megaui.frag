@@ -2,14 +2,45 @@
 
 layout(location = 0) in vec2 v_Uv;
 layout(location = 1) in vec4 v_Color;
+layout(location = 2) in vec2 v_WindowLocal;
+layout(location = 3) in vec3 v_WindowShape;
 
 layout(location = 0) out vec4 o_Target;
 
+layout(set = 0, binding = 2) uniform MegaUiColorTransform {
+    vec4 ui_color_scale;
+    mat4 ui_color_matrix;
+};
+
 layout(set = 1, binding = 0) uniform texture2D MegaUiTexture_texture;
 layout(set = 1, binding = 1) uniform sampler MegaUiTexture_texture_sampler;
 
+// The color matrix is affine, its last column is an offset. The offset and the output range are
+// scaled by alpha of premultiplied colors, which is indicated by the last component of the column.
+vec3 filterColor(vec4 color) {
+    float alphaScale = mix(1.0, color.a, ui_color_matrix[3].w);
+    vec3 filtered = mat3(ui_color_matrix) * color.rgb + ui_color_matrix[3].rgb * alphaScale;
+    return clamp(filtered, 0.0, alphaScale);
+}
+
+// Cuts the rounded corners of the window the fragment belongs to. Only the corner squares are
+// affected, so the geometry outside of the window rect isn't clipped.
+float cornerCoverage(vec2 local, vec3 shape) {
+    float radius = shape.z;
+    vec2 q = abs(local) - shape.xy + radius;
+    float distance = length(max(q, 0.0)) - radius;
+    // The arc is smoothed over a pixel outside the window, as its straight edges aren't smoothed.
+    float pixel = fwidth(distance);
+    float coverage = 1.0 - smoothstep(-pixel, pixel, distance - pixel);
+    return (radius > 0.0 && q.x > 0.0 && q.y > 0.0) ? coverage : 1.0;
+}
+
 void main() {
-    o_Target = v_Color * texture(
+    vec4 color = v_Color * ui_color_scale * texture(
         sampler2D(MegaUiTexture_texture, MegaUiTexture_texture_sampler),
         v_Uv);
+    // Premultiplied colors are scaled as a whole.
+    float coverage = cornerCoverage(v_WindowLocal, v_WindowShape);
+    color *= mix(vec4(1.0, 1.0, 1.0, coverage), vec4(coverage), ui_color_matrix[3].w);
+    o_Target = vec4(filterColor(color), color.a);
 }
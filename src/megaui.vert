@@ -3,17 +3,32 @@
 layout(location = 0) in vec3 Vertex_Position;
 layout(location = 1) in vec2 Vertex_Uv;
 layout(location = 2) in vec4 Vertex_Color;
+layout(location = 3) in vec4 Vertex_WindowRect;
+layout(location = 4) in float Vertex_CornerRadius;
 
 layout(location = 0) out vec2 v_Uv;
 layout(location = 1) out vec4 v_Color;
+// Position relative to the window center.
+layout(location = 2) out vec2 v_WindowLocal;
+// Half size of the window and corner radius.
+layout(location = 3) out vec3 v_WindowShape;
 
 layout(set = 0, binding = 0) uniform MegaUiTransform {
     vec2 scale;
     vec2 translation;
 };
 
+layout(set = 0, binding = 1) uniform MegaUiAffineTransform {
+    vec2 ui_x_axis;
+    vec2 ui_y_axis;
+    vec2 ui_translation;
+};
+
 void main() {
     v_Uv = Vertex_Uv;
     v_Color = Vertex_Color;
-    gl_Position = vec4(Vertex_Position * vec3(scale, 1.0) + vec3(translation, 0.0), 1.0);
+    v_WindowLocal = Vertex_Position.xy - Vertex_WindowRect.xy;
+    v_WindowShape = vec3(Vertex_WindowRect.zw, Vertex_CornerRadius);
+    vec2 position = ui_x_axis * Vertex_Position.x + ui_y_axis * Vertex_Position.y + ui_translation;
+    gl_Position = vec4(position * scale + translation, Vertex_Position.z, 1.0);
 }
@@ -0,0 +1,49 @@
+#version 300 es
+precision mediump float;
+
+in vec2 v_Uv;
+in vec4 v_Color;
+in vec2 v_BlurRadius;
+in float v_Opacity;
+in vec2 v_Local;
+in vec3 v_Shape;
+
+out vec4 o_Target;
+
+uniform sampler2D MegaUiBackground_texture;
+
+// Number of samples on each side of the kernel center.
+const int SAMPLES = 3;
+
+vec3 encodeSRGB(vec3 linearRGB) {
+    vec3 a = 12.92 * linearRGB;
+    vec3 b = 1.055 * pow(linearRGB, vec3(1.0 / 2.4)) - 0.055;
+    vec3 c = step(vec3(0.0031308), linearRGB);
+    return mix(a, b, c);
+}
+
+float roundedRectDistance(vec2 position, vec2 halfSize, float radius) {
+    vec2 q = abs(position) - halfSize + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main() {
+    // Rounded corners are smoothed over a pixel outside the window, like the UI geometry.
+    float distance = roundedRectDistance(v_Local, v_Shape.xy, v_Shape.z);
+    float pixel = fwidth(distance);
+    float coverage = 1.0 - smoothstep(-pixel, pixel, distance - pixel);
+    // The background texture already contains sRGB encoded colors.
+    vec3 color = vec3(0.0);
+    float total_weight = 0.0;
+    for (int x = -SAMPLES; x <= SAMPLES; x++) {
+        for (int y = -SAMPLES; y <= SAMPLES; y++) {
+            vec2 offset = vec2(x, y) / float(SAMPLES);
+            float weight = exp(-2.0 * dot(offset, offset));
+            color += weight * texture(MegaUiBackground_texture, v_Uv + offset * v_BlurRadius).rgb;
+            total_weight += weight;
+        }
+    }
+    // The blur is blended with premultiplied alpha, so it's faded out as a whole.
+    o_Target = vec4(mix(color / total_weight, encodeSRGB(v_Color.rgb), v_Color.a), 1.0)
+        * v_Opacity * coverage;
+}
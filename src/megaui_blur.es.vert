@@ -0,0 +1,42 @@
+#version 300 es
+
+in vec3 Vertex_Position;
+in vec4 Vertex_Color;
+in vec2 Vertex_BlurRadius;
+in vec2 Vertex_Local;
+in vec3 Vertex_Shape;
+
+out vec2 v_Uv;
+out vec4 v_Color;
+out vec2 v_BlurRadius;
+out float v_Opacity;
+out vec2 v_Local;
+out vec3 v_Shape;
+
+layout(std140) uniform MegaUiTransform {
+    vec2 scale;
+    vec2 translation;
+};
+
+layout(std140) uniform MegaUiAffineTransform {
+    vec2 ui_x_axis;
+    vec2 ui_y_axis;
+    vec2 ui_translation;
+};
+
+layout(std140) uniform MegaUiColorTransform {
+    vec4 ui_color_scale;
+    mat4 ui_color_matrix;
+};
+
+void main() {
+    vec2 position = ui_x_axis * Vertex_Position.x + ui_y_axis * Vertex_Position.y + ui_translation;
+    gl_Position = vec4(position * scale + translation, Vertex_Position.z, 1.0);
+    // The scene color is sampled under the projected position.
+    v_Uv = vec2(gl_Position.x * 0.5 + 0.5, 0.5 - gl_Position.y * 0.5);
+    v_Color = Vertex_Color;
+    v_BlurRadius = Vertex_BlurRadius;
+    v_Opacity = ui_color_scale.a;
+    v_Local = Vertex_Local;
+    v_Shape = Vertex_Shape;
+}
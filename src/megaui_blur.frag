@@ -0,0 +1,42 @@
+#version 450
+
+layout(location = 0) in vec2 v_Uv;
+layout(location = 1) in vec4 v_Color;
+layout(location = 2) in vec2 v_BlurRadius;
+layout(location = 3) in float v_Opacity;
+layout(location = 4) in vec2 v_Local;
+layout(location = 5) in vec3 v_Shape;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 1, binding = 0) uniform texture2D MegaUiBackground_texture;
+layout(set = 1, binding = 1) uniform sampler MegaUiBackground_texture_sampler;
+
+// Number of samples on each side of the kernel center.
+const int SAMPLES = 3;
+
+float roundedRectDistance(vec2 position, vec2 halfSize, float radius) {
+    vec2 q = abs(position) - halfSize + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main() {
+    // Rounded corners are smoothed over a pixel outside the window, like the UI geometry.
+    float distance = roundedRectDistance(v_Local, v_Shape.xy, v_Shape.z);
+    float pixel = fwidth(distance);
+    float coverage = 1.0 - smoothstep(-pixel, pixel, distance - pixel);
+    vec3 color = vec3(0.0);
+    float total_weight = 0.0;
+    for (int x = -SAMPLES; x <= SAMPLES; x++) {
+        for (int y = -SAMPLES; y <= SAMPLES; y++) {
+            vec2 offset = vec2(x, y) / float(SAMPLES);
+            float weight = exp(-2.0 * dot(offset, offset));
+            color += weight * texture(
+                sampler2D(MegaUiBackground_texture, MegaUiBackground_texture_sampler),
+                v_Uv + offset * v_BlurRadius).rgb;
+            total_weight += weight;
+        }
+    }
+    // The blur is blended with premultiplied alpha, so it's faded out as a whole.
+    o_Target = vec4(mix(color / total_weight, v_Color.rgb, v_Color.a), 1.0) * v_Opacity * coverage;
+}
@@ -0,0 +1,44 @@
+#version 450
+
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec4 Vertex_Color;
+layout(location = 2) in vec2 Vertex_BlurRadius;
+layout(location = 3) in vec2 Vertex_Local;
+layout(location = 4) in vec3 Vertex_Shape;
+
+layout(location = 0) out vec2 v_Uv;
+layout(location = 1) out vec4 v_Color;
+layout(location = 2) out vec2 v_BlurRadius;
+layout(location = 3) out float v_Opacity;
+// Position relative to the window center.
+layout(location = 4) out vec2 v_Local;
+// Half size of the window and corner radius.
+layout(location = 5) out vec3 v_Shape;
+
+layout(set = 0, binding = 0) uniform MegaUiTransform {
+    vec2 scale;
+    vec2 translation;
+};
+
+layout(set = 0, binding = 1) uniform MegaUiAffineTransform {
+    vec2 ui_x_axis;
+    vec2 ui_y_axis;
+    vec2 ui_translation;
+};
+
+layout(set = 0, binding = 2) uniform MegaUiColorTransform {
+    vec4 ui_color_scale;
+    mat4 ui_color_matrix;
+};
+
+void main() {
+    vec2 position = ui_x_axis * Vertex_Position.x + ui_y_axis * Vertex_Position.y + ui_translation;
+    gl_Position = vec4(position * scale + translation, Vertex_Position.z, 1.0);
+    // The scene color is sampled under the projected position.
+    v_Uv = vec2(gl_Position.x * 0.5 + 0.5, 0.5 - gl_Position.y * 0.5);
+    v_Color = Vertex_Color;
+    v_BlurRadius = Vertex_BlurRadius;
+    v_Opacity = ui_color_scale.a;
+    v_Local = Vertex_Local;
+    v_Shape = Vertex_Shape;
+}
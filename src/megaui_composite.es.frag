@@ -0,0 +1,13 @@
+#version 300 es
+precision mediump float;
+
+in vec2 v_Uv;
+
+out vec4 o_Target;
+
+uniform sampler2D MegaUiComposite_texture;
+
+void main() {
+    // The texture already contains sRGB encoded colors written by the megaui shaders.
+    o_Target = texture(MegaUiComposite_texture, v_Uv);
+}
@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in vec2 v_Uv;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform texture2D MegaUiComposite_texture;
+layout(set = 0, binding = 1) uniform sampler MegaUiComposite_texture_sampler;
+
+void main() {
+    o_Target = texture(
+        sampler2D(MegaUiComposite_texture, MegaUiComposite_texture_sampler),
+        v_Uv);
+}
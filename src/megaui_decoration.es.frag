@@ -0,0 +1,31 @@
+#version 300 es
+precision mediump float;
+
+// Position relative to the rect center.
+in vec2 v_Local;
+in vec4 v_Color;
+// Half size of the rect, corner radius and blur radius.
+in vec4 v_Shape;
+
+out vec4 o_Target;
+
+vec3 encodeSRGB(vec3 linearRGB) {
+    vec3 a = 12.92 * linearRGB;
+    vec3 b = 1.055 * pow(linearRGB, vec3(1.0 / 2.4)) - 0.055;
+    vec3 c = step(vec3(0.0031308), linearRGB);
+    return mix(a, b, c);
+}
+
+float roundedRectDistance(vec2 position, vec2 halfSize, float radius) {
+    vec2 q = abs(position) - halfSize + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main() {
+    float distance = roundedRectDistance(v_Local, v_Shape.xy, v_Shape.z);
+    // The edge is smoothed over the blur radius or a pixel outside the rect.
+    float pixel = fwidth(distance);
+    float softness = max(v_Shape.w, pixel);
+    float alpha = v_Color.a * (1.0 - smoothstep(-softness, softness, distance - pixel));
+    o_Target = vec4(encodeSRGB(v_Color.rgb) * alpha, alpha);
+}
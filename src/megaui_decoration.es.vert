@@ -0,0 +1,29 @@
+#version 300 es
+
+in vec3 Vertex_Position;
+in vec2 Vertex_Local;
+in vec4 Vertex_Color;
+in vec4 Vertex_Shape;
+
+out vec2 v_Local;
+out vec4 v_Color;
+out vec4 v_Shape;
+
+layout(std140) uniform MegaUiTransform {
+    vec2 scale;
+    vec2 translation;
+};
+
+layout(std140) uniform MegaUiAffineTransform {
+    vec2 ui_x_axis;
+    vec2 ui_y_axis;
+    vec2 ui_translation;
+};
+
+void main() {
+    v_Local = Vertex_Local;
+    v_Color = Vertex_Color;
+    v_Shape = Vertex_Shape;
+    vec2 position = ui_x_axis * Vertex_Position.x + ui_y_axis * Vertex_Position.y + ui_translation;
+    gl_Position = vec4(position * scale + translation, Vertex_Position.z, 1.0);
+}
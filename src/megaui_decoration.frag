@@ -0,0 +1,23 @@
+#version 450
+
+// Position relative to the rect center.
+layout(location = 0) in vec2 v_Local;
+layout(location = 1) in vec4 v_Color;
+// Half size of the rect, corner radius and blur radius.
+layout(location = 2) in vec4 v_Shape;
+
+layout(location = 0) out vec4 o_Target;
+
+float roundedRectDistance(vec2 position, vec2 halfSize, float radius) {
+    vec2 q = abs(position) - halfSize + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main() {
+    float distance = roundedRectDistance(v_Local, v_Shape.xy, v_Shape.z);
+    // The edge is smoothed over the blur radius or a pixel outside the rect.
+    float pixel = fwidth(distance);
+    float softness = max(v_Shape.w, pixel);
+    float alpha = v_Color.a * (1.0 - smoothstep(-softness, softness, distance - pixel));
+    o_Target = vec4(v_Color.rgb * alpha, alpha);
+}
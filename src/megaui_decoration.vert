@@ -0,0 +1,29 @@
+#version 450
+
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec2 Vertex_Local;
+layout(location = 2) in vec4 Vertex_Color;
+layout(location = 3) in vec4 Vertex_Shape;
+
+layout(location = 0) out vec2 v_Local;
+layout(location = 1) out vec4 v_Color;
+layout(location = 2) out vec4 v_Shape;
+
+layout(set = 0, binding = 0) uniform MegaUiTransform {
+    vec2 scale;
+    vec2 translation;
+};
+
+layout(set = 0, binding = 1) uniform MegaUiAffineTransform {
+    vec2 ui_x_axis;
+    vec2 ui_y_axis;
+    vec2 ui_translation;
+};
+
+void main() {
+    v_Local = Vertex_Local;
+    v_Color = Vertex_Color;
+    v_Shape = Vertex_Shape;
+    vec2 position = ui_x_axis * Vertex_Position.x + ui_y_axis * Vertex_Position.y + ui_translation;
+    gl_Position = vec4(position * scale + translation, Vertex_Position.z, 1.0);
+}
@@ -1,6 +1,15 @@
 use crate::{
-    MegaUiContext, MegaUiSettings, WindowSize, MEGAUI_PIPELINE_HANDLE,
-    MEGAUI_TEXTURE_RESOURCE_BINDING_NAME, MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME,
+    texture::{
+        align_rows, mip_level_size, prepare_texture_data, prepare_texture_region_data, TextureData,
+        TextureDataError, TextureRegion,
+    },
+    window_resource_binding_name, HotReloadedShaders, MegaUiAffineTransform, MegaUiContext,
+    MegaUiContexts, MegaUiPipelineConfig, MegaUiRenderStats, MegaUiSettings, MegaUiTexture,
+    MegaUiTextureUploadBudget, MegaUiViewport, MegaUiWindowRenderStats, WindowSize, WindowSizes,
+    MEGAUI_AFFINE_TRANSFORM_RESOURCE_BINDING_NAME, MEGAUI_BLUR_PIPELINE_HANDLE,
+    MEGAUI_COLOR_TRANSFORM_RESOURCE_BINDING_NAME, MEGAUI_COMPOSITE_PIPELINE_HANDLE,
+    MEGAUI_PIPELINE_HANDLE, MEGAUI_SHADOW_PIPELINE_HANDLE, MEGAUI_TEXTURE_RESOURCE_BINDING_NAME,
+    MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME,
 };
 use bevy::{
     app::{EventReader, Events},
@@ -9,9 +18,11 @@ use bevy::{
     ecs::{Resources, World},
     log,
     render::{
+        color::Color,
         pass::{
-            ClearColor, LoadOp, Operations, PassDescriptor,
-            RenderPassDepthStencilAttachmentDescriptor, TextureAttachment,
+            ClearColor, LoadOp, Operations, PassDescriptor, RenderPass,
+            RenderPassColorAttachmentDescriptor, RenderPassDepthStencilAttachmentDescriptor,
+            TextureAttachment,
         },
         pipeline::{
             BindGroupDescriptor, IndexFormat, InputStepMode, PipelineCompiler, PipelineDescriptor,
@@ -23,125 +34,260 @@ use bevy::{
             BindGroup, BufferId, BufferInfo, BufferUsage, RenderContext, RenderResourceBinding,
             RenderResourceBindings, RenderResourceType, SamplerId, TextureId,
         },
-        shader::Shader,
-        texture::{Texture, TextureDescriptor},
+        shader::{Shader, ShaderError, ShaderStages},
+        texture::{
+            Extent3d, FilterMode, SamplerDescriptor, Texture, TextureDescriptor, TextureDimension,
+            TextureFormat, TextureUsage,
+        },
     },
+    window::{WindowId, Windows},
 };
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
 };
 
+const MEGAUI_COMPOSITE_TEXTURE_RESOURCE_BINDING_NAME: &str = "MegaUiComposite_texture";
+const MEGAUI_BACKGROUND_TEXTURE_RESOURCE_BINDING_NAME: &str = "MegaUiBackground_texture";
+const WINDOW_UNIFORM_BINDING_NAMES: &[&str] = &[
+    MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME,
+    MEGAUI_AFFINE_TRANSFORM_RESOURCE_BINDING_NAME,
+    MEGAUI_COLOR_TRANSFORM_RESOURCE_BINDING_NAME,
+];
+
 pub struct MegaUiNode {
+    window_id: WindowId,
+    attachments: MegaUiPassAttachments,
     pass_descriptor: PassDescriptor,
+    // Is used for compositing the render target onto the window, see `Self::process_attachments`.
+    composite_pass_descriptor: PassDescriptor,
+    // Is set once an error about the depth sample count not matching `Msaa` has been logged.
+    depth_error_logged: bool,
+    source_pipeline_descriptor: Handle<PipelineDescriptor>,
     pipeline_descriptor: Option<Handle<PipelineDescriptor>>,
+    pipeline_sample_count: u32,
+    shader_event_reader: EventReader<AssetEvent<Shader>>,
     inputs: Vec<ResourceSlotInfo>,
-    color_attachment_input_indices: Vec<Option<usize>>,
-    color_resolve_target_indices: Vec<Option<usize>>,
-    depth_stencil_attachment_input_index: Option<usize>,
-    default_clear_color_inputs: Vec<usize>,
 
     transform_bind_group_descriptor: Option<BindGroupDescriptor>,
     transform_bind_group: Option<BindGroup>,
 
-    font_texture: Handle<Texture>,
     texture_bind_group_descriptor: Option<BindGroupDescriptor>,
-    texture_resources: HashMap<Handle<Texture>, TextureResource>,
+    texture_resources: HashMap<TextureKey, TextureResource>,
+    // Textures that can't be displayed (with their generations), we don't try to create them again
+    // until they are modified or registered again.
+    invalid_textures: HashMap<TextureKey, (Handle<Texture>, u64)>,
+    // Textures evicted due to `MegaUiSettings::texture_memory_budget` (with their generations),
+    // they are re-created only once they are drawn again.
+    evicted_textures: HashMap<TextureKey, u64>,
+    // Is incremented every update, textures store the frame they were drawn last.
+    frame: u64,
+    // See `MegaUiSettings::texture_upload_budget`.
+    texture_upload_budget: Option<MegaUiTextureUploadBudget>,
+    // Loaded textures that don't fit into the upload budget during the current frame.
+    queued_textures: HashSet<TextureKey>,
+    // Is drawn instead of queued textures.
+    placeholder_texture: Option<TextureResource>,
+    // Whether textures and vertex colors are converted to premultiplied alpha, depends on
+    // `MegaUiPipelineConfig::blend_mode`.
+    premultiplied_alpha: bool,
     event_reader: EventReader<AssetEvent<Texture>>,
 
     vertex_buffer: Option<BufferId>,
     index_buffer: Option<BufferId>,
+
+    // Is set if the UI is rendered at a reduced resolution, see `MegaUiSettings::resolution_scale`.
+    render_target: Option<RenderTarget>,
+    composite_pipeline_descriptor: Option<Handle<PipelineDescriptor>>,
+    composite_pipeline_sample_count: u32,
+    composite_bind_group_descriptor: Option<BindGroupDescriptor>,
+
+    // Is set if window backgrounds are blurred, see `MegaUiSettings::background_blur`.
+    background_blur: Option<BackgroundBlur>,
+    // Is set once a warning about the background blur being unable to run has been logged.
+    blur_warning_logged: bool,
+    blur_pipeline_descriptor: Option<Handle<PipelineDescriptor>>,
+    blur_pipeline_sample_count: u32,
+    blur_transform_bind_group_descriptor: Option<BindGroupDescriptor>,
+    blur_transform_bind_group: Option<BindGroup>,
+    blur_texture_bind_group_descriptor: Option<BindGroupDescriptor>,
+
+    // See `MegaUiSettings::window_decorations`.
+    window_decorations: WindowDecorations,
+    shadow_pipeline_descriptor: Option<Handle<PipelineDescriptor>>,
+    decoration_pipeline_sample_count: u32,
+    decoration_transform_bind_group_descriptor: Option<BindGroupDescriptor>,
+    decoration_transform_bind_group: Option<BindGroup>,
+
+    stats: MegaUiWindowRenderStats,
 }
 
+// Describes the textures a megaui pass is connected to.
+#[derive(Clone, Debug)]
+pub struct MegaUiPassAttachments {
+    pub depth_operations: Option<Operations<f32>>,
+    // Swap chain textures can't be copied, so backgrounds can't be blurred if they are rendered to.
+    pub color_source_is_swap_chain: bool,
+    // Nothing else renders to a megaui-owned swap chain, so it's cleared before the UI is drawn.
+    pub clear_color_source: bool,
+    // Megaui-owned sampled color attachments (of secondary windows) follow `Msaa`, but they
+    // don't contain the scene.
+    pub owned_sampled_color_attachment: bool,
+    // The sample count of a depth texture that doesn't follow `Msaa` (the base graph one).
+    pub fixed_depth_sample_count: Option<u32>,
+}
+
+// A copy of the scene color that is sampled by the window backgrounds, and their geometry.
 #[derive(Debug)]
-pub struct TextureResource {
-    descriptor: TextureDescriptor,
+struct BackgroundBlur {
+    size: (u32, u32),
     texture: TextureId,
     sampler: SamplerId,
     bind_group: BindGroup,
+    vertex_buffer: Option<BufferId>,
+    index_buffer: Option<BufferId>,
+    indices_count: u32,
 }
 
-impl MegaUiNode {
-    pub fn new(msaa: &Msaa, font_texture: Handle<Texture>) -> Self {
-        let color_attachments = vec![msaa.color_attachment_descriptor(
-            TextureAttachment::Input("color_attachment".to_string()),
-            TextureAttachment::Input("color_resolve_target".to_string()),
-            Operations {
-                load: LoadOp::Load,
-                store: true,
-            },
-        )];
-        let depth_stencil_attachment = RenderPassDepthStencilAttachmentDescriptor {
-            attachment: TextureAttachment::Input("depth".to_string()),
-            depth_ops: Some(Operations {
-                load: LoadOp::Clear(1.0),
-                store: true,
-            }),
-            stencil_ops: None,
-        };
-
-        let mut inputs = Vec::new();
-        let mut color_attachment_input_indices = Vec::new();
-        let mut color_resolve_target_indices = Vec::new();
+// An intermediate texture the UI is rendered to before being composited onto the window.
+#[derive(Debug)]
+struct RenderTarget {
+    size: (u32, u32),
+    filter: FilterMode,
+    texture: TextureId,
+    sampler: SamplerId,
+    bind_group: BindGroup,
+    pass_descriptor: PassDescriptor,
+}
 
-        for color_attachment in color_attachments.iter() {
-            if let TextureAttachment::Input(ref name) = color_attachment.attachment {
-                color_attachment_input_indices.push(Some(inputs.len()));
-                inputs.push(ResourceSlotInfo::new(
-                    name.to_string(),
-                    RenderResourceType::Texture,
-                ));
-            } else {
-                color_attachment_input_indices.push(None);
-            }
+// Geometry of window shadows.
+#[derive(Debug, Default)]
+struct WindowDecorations {
+    vertex_buffer: Option<BufferId>,
+    index_buffer: Option<BufferId>,
+    shadow_indices_count: u32,
+}
 
-            if let Some(TextureAttachment::Input(ref name)) = color_attachment.resolve_target {
-                color_resolve_target_indices.push(Some(inputs.len()));
-                inputs.push(ResourceSlotInfo::new(
-                    name.to_string(),
-                    RenderResourceType::Texture,
-                ));
-            } else {
-                color_resolve_target_indices.push(None);
-            }
-        }
+#[derive(Debug)]
+pub struct TextureResource {
+    handle: Handle<Texture>,
+    generation: u64,
+    descriptor: TextureDescriptor,
+    texture: TextureId,
+    sampler: SamplerId,
+    bind_group: BindGroup,
+    size_bytes: usize,
+    last_drawn_frame: u64,
+}
 
-        let mut depth_stencil_attachment_input_index = None;
-        if let TextureAttachment::Input(ref name) = depth_stencil_attachment.attachment {
-            depth_stencil_attachment_input_index = Some(inputs.len());
+impl MegaUiNode {
+    pub const IN_COLOR_ATTACHMENT: &'static str = "color_attachment";
+    pub const IN_SAMPLED_COLOR_ATTACHMENT: &'static str = "sampled_color_attachment";
+    pub const IN_DEPTH: &'static str = "depth";
+
+    const COLOR_ATTACHMENT_INPUT_INDEX: usize = 0;
+    const SAMPLED_COLOR_ATTACHMENT_INPUT_INDEX: usize = 1;
+    const DEPTH_INPUT_INDEX: usize = 2;
+
+    pub fn new(window_id: WindowId, msaa: &Msaa, attachments: MegaUiPassAttachments) -> Self {
+        // The sampled color attachment input is used only if MSAA is enabled, which can change
+        // at runtime, so all the inputs are declared regardless of the current sample count.
+        let mut inputs = vec![
+            ResourceSlotInfo::new(Self::IN_COLOR_ATTACHMENT, RenderResourceType::Texture),
+            ResourceSlotInfo::new(
+                Self::IN_SAMPLED_COLOR_ATTACHMENT,
+                RenderResourceType::Texture,
+            ),
+        ];
+        let depth_stencil_attachment = attachments.depth_operations.map(|depth_ops| {
             inputs.push(ResourceSlotInfo::new(
-                name.to_string(),
+                Self::IN_DEPTH,
                 RenderResourceType::Texture,
             ));
-        }
+            RenderPassDepthStencilAttachmentDescriptor {
+                attachment: TextureAttachment::Input(Self::IN_DEPTH.to_string()),
+                depth_ops: Some(depth_ops),
+                stencil_ops: None,
+            }
+        });
+
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![msaa.color_attachment_descriptor(
+                TextureAttachment::Input(Self::IN_SAMPLED_COLOR_ATTACHMENT.to_string()),
+                TextureAttachment::Input(Self::IN_COLOR_ATTACHMENT.to_string()),
+                Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            )],
+            depth_stencil_attachment,
+            sample_count: msaa.samples,
+        };
 
         Self {
-            pass_descriptor: PassDescriptor {
-                color_attachments,
-                depth_stencil_attachment: Some(depth_stencil_attachment),
-                sample_count: msaa.samples,
-            },
+            window_id,
+            attachments,
+            composite_pass_descriptor: pass_descriptor.clone(),
+            pass_descriptor,
+            depth_error_logged: false,
+            source_pipeline_descriptor: MEGAUI_PIPELINE_HANDLE.typed(),
             pipeline_descriptor: None,
-            default_clear_color_inputs: Vec::new(),
+            pipeline_sample_count: 0,
+            shader_event_reader: Default::default(),
             inputs,
-            depth_stencil_attachment_input_index,
-            color_attachment_input_indices,
             transform_bind_group_descriptor: None,
             transform_bind_group: None,
-            font_texture,
             texture_bind_group_descriptor: None,
             texture_resources: Default::default(),
+            invalid_textures: Default::default(),
+            evicted_textures: Default::default(),
+            frame: 0,
+            texture_upload_budget: None,
+            queued_textures: Default::default(),
+            placeholder_texture: None,
+            premultiplied_alpha: false,
             event_reader: Default::default(),
             vertex_buffer: None,
             index_buffer: None,
-            color_resolve_target_indices,
+            render_target: None,
+            composite_pipeline_descriptor: None,
+            composite_pipeline_sample_count: 0,
+            composite_bind_group_descriptor: None,
+            background_blur: None,
+            blur_warning_logged: false,
+            blur_pipeline_descriptor: None,
+            blur_pipeline_sample_count: 0,
+            blur_transform_bind_group_descriptor: None,
+            blur_transform_bind_group: None,
+            blur_texture_bind_group_descriptor: None,
+            window_decorations: Default::default(),
+            shadow_pipeline_descriptor: None,
+            decoration_pipeline_sample_count: 0,
+            decoration_transform_bind_group_descriptor: None,
+            decoration_transform_bind_group: None,
+            stats: Default::default(),
         }
     }
 }
 
+// Identifies a texture of a context layer (`None` stands for the default context) by its megaui
+// texture id (`None` stands for the font texture).
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct TextureKey {
+    layer: Option<Cow<'static, str>>,
+    texture_id: Option<u32>,
+}
+
+// Contexts of a window ordered from the bottom to the top, see `MegaUiContexts::window_layers_mut`.
+type WindowLayers<'a> = [(Option<Cow<'static, str>>, &'a mut MegaUiContext)];
+
 struct DrawCommand {
     vertices_count: usize,
-    texture_handle: Option<Handle<Texture>>,
+    // Offset of the vertex batch the indices refer to, in bytes.
+    vertex_buffer_offset: u64,
+    texture_key: TextureKey,
+    // Whether the texture is queued by the upload budget, see `MegaUiNode::queued_textures`.
+    is_texture_pending: bool,
     clipping_zone: Option<megaui::Rect>,
 }
 
@@ -158,11 +304,36 @@ impl Node for MegaUiNode {
         input: &ResourceSlots,
         _output: &mut ResourceSlots,
     ) {
-        self.process_attachments(input, resources);
-        self.init_pipeline(render_context, resources);
+        self.stats = MegaUiWindowRenderStats::default();
+        self.frame += 1;
+        if resources
+            .get::<Windows>()
+            .unwrap()
+            .get(self.window_id)
+            .is_none()
+        {
+            self.remove_stats(resources);
+            return;
+        }
+        self.process_msaa(resources);
+        let clear_color = resources
+            .get::<ClearColor>()
+            .map_or(Color::BLACK, |clear_color| clear_color.0);
+        if !self.process_attachments(input, clear_color) {
+            self.remove_stats(resources);
+            return;
+        }
 
-        let window_size = resources.get::<WindowSize>().unwrap();
+        let window_size = resources.get::<WindowSizes>().unwrap().get(self.window_id);
         let megaui_settings = resources.get::<MegaUiSettings>().unwrap();
+        self.process_render_target(render_context, resources, &window_size, &megaui_settings);
+        self.init_pipeline(render_context, resources);
+        self.premultiplied_alpha = resources
+            .get::<MegaUiPipelineConfig>()
+            .unwrap()
+            .blend_mode
+            .premultiplied_alpha();
+        self.texture_upload_budget = megaui_settings.texture_upload_budget;
 
         let render_resource_bindings = resources.get::<RenderResourceBindings>().unwrap();
 
@@ -171,60 +342,224 @@ impl Node for MegaUiNode {
         let texture_assets = resources.get_mut::<Assets<Texture>>().unwrap();
         let asset_events = resources.get::<Events<AssetEvent<Texture>>>().unwrap();
 
-        let mut megaui_context = resources.get_thread_local_mut::<MegaUiContext>().unwrap();
-
-        self.process_asset_events(
-            render_context,
-            &mut megaui_context,
-            &asset_events,
-            &texture_assets,
+        let mut primary_context = resources.get_thread_local_mut::<MegaUiContext>().unwrap();
+        let mut megaui_contexts = resources.get_thread_local_mut::<MegaUiContexts>().unwrap();
+        let mut layers = megaui_contexts.window_layers_mut(
+            &mut primary_context,
+            self.window_id,
+            &megaui_settings,
         );
-        self.remove_unused_textures(render_context, &megaui_context);
-        self.init_textures(render_context, &megaui_context, &texture_assets);
+        if layers.is_empty() {
+            self.remove_stats(resources);
+            return;
+        }
 
-        megaui_context.render_draw_lists();
-        let mut ui_draw_lists = Vec::new();
+        let mut window_rects = Vec::new();
+        // Window rects of the draw lists of each visible context.
+        let mut draw_list_windows = Vec::new();
+        for (_, megaui_context) in layers.iter_mut() {
+            // Hidden contexts are still rendered, so that their frames are finished.
+            megaui_context.render_draw_lists();
+            let mut context_window_rects = std::mem::take(&mut megaui_context.window_rects);
+            if megaui_context.visible {
+                sort_window_rects(&mut context_window_rects, &megaui_context.ui_draw_lists);
+                draw_list_windows.push(assign_draw_lists_to_windows(
+                    &context_window_rects,
+                    &megaui_context.ui_draw_lists,
+                ));
+                window_rects.extend(context_window_rects);
+            }
+        }
+        let drawn_textures = drawn_texture_keys(&layers);
 
-        std::mem::swap(&mut ui_draw_lists, &mut megaui_context.ui_draw_lists);
+        self.process_asset_events(render_context, &mut layers, &asset_events, &texture_assets);
+        for (_, megaui_context) in layers.iter_mut() {
+            megaui_context.dirty_texture_rects.clear();
+        }
+        self.remove_unused_textures(render_context, &layers);
+        self.init_textures(render_context, &layers, &drawn_textures, &texture_assets);
+        self.init_placeholder_texture(render_context);
+        self.evict_textures(render_context, &megaui_settings);
+        self.prepare_background_blur(
+            render_context,
+            resources,
+            input,
+            &window_size,
+            &megaui_settings,
+            &window_rects,
+        );
+        self.prepare_window_decorations(render_context, resources, &megaui_settings, &window_rects);
+        let visible_layers = layers
+            .iter()
+            .filter(|(_, megaui_context)| megaui_context.visible)
+            .collect::<Vec<_>>();
 
         let mut vertex_buffer = Vec::<u8>::new();
         let mut index_buffer = Vec::new();
         let mut draw_commands = Vec::new();
         let mut index_offset = 0;
+        let mut vertex_buffer_offset = 0;
 
-        for draw_list in &ui_draw_lists {
-            let texture_handle = if let Some(texture) = draw_list.texture {
-                megaui_context.megaui_textures.get(&texture).cloned()
-            } else {
-                Some(megaui_context.font_texture.clone())
-            };
-
-            for vertex in &draw_list.vertices {
-                vertex_buffer.extend_from_slice(vertex.pos.as_bytes());
-                vertex_buffer.extend_from_slice(vertex.uv.as_bytes());
-                vertex_buffer.extend_from_slice(vertex.color.as_bytes());
+        let corner_radius = megaui_settings
+            .window_decorations
+            .map_or(0.0, |window_decorations| window_decorations.corner_radius);
+        for ((layer, megaui_context), draw_list_windows) in
+            visible_layers.iter().zip(&draw_list_windows)
+        {
+            for (draw_list, window_rect) in
+                megaui_context.ui_draw_lists.iter().zip(draw_list_windows)
+            {
+                // Rounded corners of the window are cut in the fragment shader, draw lists that
+                // don't belong to a window get a zero radius and aren't cut.
+                let (window_shape, window_corner_radius) = match window_rect {
+                    Some(window_rect) => {
+                        let (center, half_size, corner_radius) =
+                            rounded_rect(*window_rect, corner_radius);
+                        (
+                            [center[0], center[1], half_size[0], half_size[1]],
+                            corner_radius,
+                        )
+                    }
+                    None => ([0.0; 4], 0.0),
+                };
+                let texture_key = TextureKey {
+                    layer: layer.clone(),
+                    texture_id: draw_list.texture,
+                };
+                let uv_rect = self.texture_uv_rect(megaui_context, &texture_key);
+                let is_texture_pending = self.queued_textures.contains(&texture_key);
+                // Indices are 16-bit, so a new vertex batch is started before they overflow.
+                // Its vertices are bound at an offset, which works on WebGL2 as well, unlike
+                // a base vertex.
+                if index_offset + draw_list.vertices.len() > u16::MAX as usize + 1 {
+                    index_offset = 0;
+                    vertex_buffer_offset = vertex_buffer.len() as u64;
+                }
+                for vertex in &draw_list.vertices {
+                    let uv = uv_rect.map_or(vertex.uv, |uv_rect| {
+                        [
+                            uv_rect.x + vertex.uv[0] * uv_rect.w,
+                            uv_rect.y + vertex.uv[1] * uv_rect.h,
+                        ]
+                    });
+                    vertex_buffer.extend_from_slice(vertex.pos.as_bytes());
+                    vertex_buffer.extend_from_slice(uv.as_bytes());
+                    if self.premultiplied_alpha {
+                        let [r, g, b, a] = vertex.color;
+                        vertex_buffer.extend_from_slice([r * a, g * a, b * a, a].as_bytes());
+                    } else {
+                        vertex_buffer.extend_from_slice(vertex.color.as_bytes());
+                    }
+                    vertex_buffer.extend_from_slice(window_shape.as_bytes());
+                    vertex_buffer.extend_from_slice(window_corner_radius.as_bytes());
+                }
+                let indices_with_offset = draw_list
+                    .indices
+                    .iter()
+                    .map(|i| i + index_offset as u16)
+                    .collect::<Vec<_>>();
+                index_buffer.extend_from_slice(indices_with_offset.as_slice().as_bytes());
+                index_offset += draw_list.vertices.len();
+
+                draw_commands.push(DrawCommand {
+                    vertices_count: draw_list.indices.len(),
+                    vertex_buffer_offset,
+                    texture_key,
+                    is_texture_pending,
+                    clipping_zone: draw_list.clipping_zone,
+                });
             }
-            let indices_with_offset = draw_list
-                .indices
-                .iter()
-                .map(|i| i + index_offset)
-                .collect::<Vec<_>>();
-            index_buffer.extend_from_slice(indices_with_offset.as_slice().as_bytes());
-            index_offset += draw_list.vertices.len() as u16;
-
-            draw_commands.push(DrawCommand {
-                vertices_count: draw_list.indices.len(),
-                texture_handle,
-                clipping_zone: draw_list.clipping_zone,
-            });
         }
 
         self.update_buffers(render_context, &vertex_buffer, &index_buffer);
 
+        self.stats.vertices = visible_layers
+            .iter()
+            .flat_map(|(_, megaui_context)| &megaui_context.ui_draw_lists)
+            .map(|draw_list| draw_list.vertices.len())
+            .sum();
+        self.stats.indices = draw_commands
+            .iter()
+            .map(|draw_command| draw_command.vertices_count)
+            .sum();
+        self.stats.draw_calls = draw_commands
+            .iter()
+            .filter(|draw_command| self.texture_bind_group(draw_command).is_some())
+            .count();
+        self.stats.draw_calls += [
+            self.window_decorations.shadow_indices_count,
+            self.background_blur_indices_count(),
+        ]
+        .iter()
+        .filter(|&&indices_count| indices_count > 0)
+        .count();
+        self.stats.textures = self.texture_resources.len();
+        self.stats.texture_bytes = self.texture_bytes();
+        self.stats.buffer_bytes = vertex_buffer.len() + index_buffer.len();
+        resources
+            .get_mut::<MegaUiRenderStats>()
+            .unwrap()
+            .windows
+            .insert(self.window_id, self.stats);
+
+        let (target_size, ui_pass_descriptor) = match &self.render_target {
+            Some(render_target) => (render_target.size, &render_target.pass_descriptor),
+            None => (
+                (
+                    window_size.physical_width as u32,
+                    window_size.physical_height as u32,
+                ),
+                &self.pass_descriptor,
+            ),
+        };
+        // The window is mapped to the whole render target by the transform, so the scale
+        // is derived from the target size, which can be rounded if the resolution is reduced.
+        let scissor_mapping = ScissorMapping {
+            viewport: megaui_settings.viewport(self.window_id, &window_size),
+            transform: megaui_settings.transform(self.window_id),
+            ui_scale: megaui_settings.scale_factor as f32,
+            pixel_scale: (
+                target_size.0 as f32 / window_size.width(),
+                target_size.1 as f32 / window_size.height(),
+            ),
+            target_size,
+        };
+
         render_context.begin_pass(
-            &self.pass_descriptor,
+            ui_pass_descriptor,
             &render_resource_bindings,
             &mut |render_pass| {
+                let window_decorations = &self.window_decorations;
+                if window_decorations.shadow_indices_count > 0 {
+                    render_pass.set_pipeline(self.shadow_pipeline_descriptor.as_ref().unwrap());
+                    self.set_decoration_buffers(render_pass);
+                    render_pass.draw_indexed(0..window_decorations.shadow_indices_count, 0, 0..1);
+                }
+
+                if let Some(background_blur) = &self.background_blur {
+                    if background_blur.indices_count > 0 {
+                        render_pass.set_pipeline(self.blur_pipeline_descriptor.as_ref().unwrap());
+                        render_pass.set_vertex_buffer(0, background_blur.vertex_buffer.unwrap(), 0);
+                        render_pass.set_index_buffer(background_blur.index_buffer.unwrap(), 0);
+                        render_pass.set_bind_group(
+                            0,
+                            self.blur_transform_bind_group_descriptor
+                                .as_ref()
+                                .unwrap()
+                                .id,
+                            self.blur_transform_bind_group.as_ref().unwrap().id,
+                            None,
+                        );
+                        render_pass.set_bind_group(
+                            1,
+                            self.blur_texture_bind_group_descriptor.as_ref().unwrap().id,
+                            background_blur.bind_group.id,
+                            None,
+                        );
+                        render_pass.draw_indexed(0..background_blur.indices_count, 0, 0..1);
+                    }
+                }
+
                 render_pass.set_pipeline(self.pipeline_descriptor.as_ref().unwrap());
                 render_pass.set_vertex_buffer(0, self.vertex_buffer.unwrap(), 0);
                 render_pass.set_index_buffer(self.index_buffer.unwrap(), 0);
@@ -237,7 +572,11 @@ impl Node for MegaUiNode {
 
                 // This is a pretty weird kludge, but we need to bind all our groups at least once,
                 // so they don't get garbage collected by `remove_stale_bind_groups`.
-                for texture_resource in self.texture_resources.values() {
+                for texture_resource in self
+                    .texture_resources
+                    .values()
+                    .chain(self.placeholder_texture.as_ref())
+                {
                     render_pass.set_bind_group(
                         1,
                         self.texture_bind_group_descriptor.as_ref().unwrap().id,
@@ -247,43 +586,34 @@ impl Node for MegaUiNode {
                 }
 
                 let mut vertex_offset: u32 = 0;
+                let mut bound_vertex_buffer_offset = 0;
                 for draw_command in &draw_commands {
-                    let texture_resource = match draw_command
-                        .texture_handle
-                        .as_ref()
-                        .and_then(|texture_handle| self.texture_resources.get(texture_handle))
-                    {
-                        Some(texture_resource) => texture_resource,
+                    let texture_bind_group = match self.texture_bind_group(draw_command) {
+                        Some(texture_bind_group) => texture_bind_group,
                         None => {
                             vertex_offset += draw_command.vertices_count as u32;
                             continue;
                         }
                     };
 
+                    if draw_command.vertex_buffer_offset != bound_vertex_buffer_offset {
+                        render_pass.set_vertex_buffer(
+                            0,
+                            self.vertex_buffer.unwrap(),
+                            draw_command.vertex_buffer_offset,
+                        );
+                        bound_vertex_buffer_offset = draw_command.vertex_buffer_offset;
+                    }
+
                     render_pass.set_bind_group(
                         1,
                         self.texture_bind_group_descriptor.as_ref().unwrap().id,
-                        texture_resource.bind_group.id,
+                        texture_bind_group.id,
                         None,
                     );
 
-                    let scale_factor =
-                        window_size.scale_factor * megaui_settings.scale_factor as f32;
-                    if let Some(clipping_zone) = draw_command.clipping_zone {
-                        render_pass.set_scissor_rect(
-                            (clipping_zone.x * scale_factor) as u32,
-                            (clipping_zone.y * scale_factor) as u32,
-                            (clipping_zone.w * scale_factor) as u32,
-                            (clipping_zone.h * scale_factor) as u32,
-                        );
-                    } else {
-                        render_pass.set_scissor_rect(
-                            0,
-                            0,
-                            window_size.physical_width as u32,
-                            window_size.physical_height as u32,
-                        );
-                    }
+                    let (x, y, w, h) = scissor_mapping.scissor_rect(draw_command.clipping_zone);
+                    render_pass.set_scissor_rect(x, y, w, h);
                     render_pass.draw_indexed(
                         vertex_offset..(vertex_offset + draw_command.vertices_count as u32),
                         0,
@@ -294,96 +624,169 @@ impl Node for MegaUiNode {
             },
         );
 
-        std::mem::swap(&mut ui_draw_lists, &mut megaui_context.ui_draw_lists);
-        megaui_context
-            .ui
-            .new_frame(resources.get::<Time>().unwrap().delta_seconds());
+        if let Some(render_target) = &self.render_target {
+            render_context.begin_pass(
+                &self.composite_pass_descriptor,
+                &render_resource_bindings,
+                &mut |render_pass| {
+                    render_pass.set_pipeline(self.composite_pipeline_descriptor.as_ref().unwrap());
+                    // The composite shaders don't read vertices, but the compiled pipeline still
+                    // has an (empty) vertex buffer layout.
+                    render_pass.set_vertex_buffer(0, self.vertex_buffer.unwrap(), 0);
+                    render_pass.set_bind_group(
+                        0,
+                        self.composite_bind_group_descriptor.as_ref().unwrap().id,
+                        render_target.bind_group.id,
+                        None,
+                    );
+                    render_pass.draw(0..3, 0..1);
+                },
+            );
+        }
+
+        let delta_seconds = resources.get::<Time>().unwrap().delta_seconds();
+        for (_, megaui_context) in layers.iter_mut() {
+            megaui_context.ui.new_frame(delta_seconds);
+        }
     }
 }
 
 impl MegaUiNode {
-    fn process_attachments(&mut self, input: &ResourceSlots, resources: &Resources) {
-        if let Some(input_index) = self.depth_stencil_attachment_input_index {
-            self.pass_descriptor
-                .depth_stencil_attachment
-                .as_mut()
-                .unwrap()
-                .attachment =
-                TextureAttachment::Id(input.get(input_index).unwrap().get_texture().unwrap());
+    // Removes the statistics of the window if nothing is rendered, so that they don't get stale
+    // (for instance, once the window is closed).
+    fn remove_stats(&self, resources: &Resources) {
+        resources
+            .get_mut::<MegaUiRenderStats>()
+            .unwrap()
+            .windows
+            .remove(&self.window_id);
+    }
+
+    // Pipelines are specialized by the sample count, so they get recompiled if it changes
+    // (see `Self::init_pipeline` and `Self::init_composite_pipeline`).
+    fn process_msaa(&mut self, resources: &Resources) {
+        let msaa = resources.get::<Msaa>().unwrap();
+        if msaa.samples == self.pass_descriptor.sample_count {
+            return;
         }
+        log::debug!(
+            "Updating the megaui pass sample count: {} -> {}",
+            self.pass_descriptor.sample_count,
+            msaa.samples
+        );
+        self.pass_descriptor.sample_count = msaa.samples;
+    }
 
-        for (i, color_attachment) in self
-            .pass_descriptor
-            .color_attachments
-            .iter_mut()
-            .enumerate()
-        {
-            if self.default_clear_color_inputs.contains(&i) {
-                if let Some(default_clear_color) = resources.get::<ClearColor>() {
-                    color_attachment.ops.load = LoadOp::Clear(default_clear_color.0);
+    // Returns `false` if some of the required attachments aren't connected (for instance, if MSAA
+    // got enabled, but there's no sampled color source), or if the depth texture doesn't match
+    // the `Msaa` sample count.
+    fn process_attachments(&mut self, input: &ResourceSlots, clear_color: Color) -> bool {
+        let texture = |index: usize| input.get(index).and_then(|resource| resource.get_texture());
+
+        let sample_count = self.pass_descriptor.sample_count;
+        if let Some(depth_stencil_attachment) = &mut self.pass_descriptor.depth_stencil_attachment {
+            match self.attachments.fixed_depth_sample_count {
+                Some(depth_sample_count) if depth_sample_count != sample_count => {
+                    if !self.depth_error_logged {
+                        log::error!(
+                            "The megaui pass isn't rendered: the depth texture sample count ({}) \
+                             doesn't match the MSAA sample count ({})",
+                            depth_sample_count,
+                            sample_count
+                        );
+                        self.depth_error_logged = true;
+                    }
+                    return false;
                 }
+                _ => self.depth_error_logged = false,
             }
-            if let Some(input_index) = self.color_attachment_input_indices[i] {
-                color_attachment.attachment =
-                    TextureAttachment::Id(input.get(input_index).unwrap().get_texture().unwrap());
+            match texture(Self::DEPTH_INPUT_INDEX) {
+                Some(depth) => depth_stencil_attachment.attachment = TextureAttachment::Id(depth),
+                None => return false,
             }
-            if let Some(input_index) = self.color_resolve_target_indices[i] {
-                color_attachment.resolve_target = Some(TextureAttachment::Id(
-                    input.get(input_index).unwrap().get_texture().unwrap(),
-                ));
+        }
+
+        let color_attachment = match texture(Self::COLOR_ATTACHMENT_INPUT_INDEX) {
+            Some(color_attachment) => color_attachment,
+            None => return false,
+        };
+        let color_ops = Operations {
+            load: if self.attachments.clear_color_source {
+                LoadOp::Clear(clear_color)
+            } else {
+                LoadOp::Load
+            },
+            store: true,
+        };
+        let multisampled = sample_count > 1;
+        let color_attachment_descriptor = &mut self.pass_descriptor.color_attachments[0];
+        if multisampled {
+            match texture(Self::SAMPLED_COLOR_ATTACHMENT_INPUT_INDEX) {
+                Some(sampled_color_attachment) => {
+                    color_attachment_descriptor.attachment =
+                        TextureAttachment::Id(sampled_color_attachment);
+                    color_attachment_descriptor.resolve_target =
+                        Some(TextureAttachment::Id(color_attachment));
+                }
+                None => return false,
             }
+        } else {
+            color_attachment_descriptor.attachment = TextureAttachment::Id(color_attachment);
+            color_attachment_descriptor.resolve_target = None;
+            color_attachment_descriptor.ops = color_ops;
         }
+
+        // Megaui-owned sampled attachments don't contain the scene, so render targets are
+        // composited onto the color attachment directly, otherwise it's done the same way
+        // the UI is drawn without a render target.
+        self.composite_pass_descriptor = if self.attachments.owned_sampled_color_attachment {
+            PassDescriptor {
+                color_attachments: vec![RenderPassColorAttachmentDescriptor {
+                    attachment: TextureAttachment::Id(color_attachment),
+                    resolve_target: None,
+                    ops: color_ops,
+                }],
+                depth_stencil_attachment: None,
+                sample_count: 1,
+            }
+        } else {
+            self.pass_descriptor.clone()
+        };
+        true
     }
 
     fn init_pipeline(&mut self, render_context: &mut dyn RenderContext, resources: &Resources) {
-        if self.pipeline_descriptor.is_some() {
+        self.process_shader_events(resources);
+        let sample_count = self.ui_sample_count();
+        if self.pipeline_descriptor.is_some() && self.pipeline_sample_count == sample_count {
             return;
         }
 
         let mut pipelines = resources.get_mut::<Assets<PipelineDescriptor>>().unwrap();
         let mut shaders = resources.get_mut::<Assets<Shader>>().unwrap();
-        let msaa = resources.get::<Msaa>().unwrap();
 
         let pipeline_descriptor_handle = {
             let render_resource_context = render_context.resources();
             let mut pipeline_compiler = resources.get_mut::<PipelineCompiler>().unwrap();
 
-            let attributes = vec![
-                VertexAttributeDescriptor {
-                    name: Cow::from("Vertex_Position"),
-                    offset: 0,
-                    format: VertexFormat::Float3,
-                    shader_location: 0,
-                },
-                VertexAttributeDescriptor {
-                    name: Cow::from("Vertex_Uv"),
-                    offset: VertexFormat::Float3.get_size(),
-                    format: VertexFormat::Float2,
-                    shader_location: 1,
-                },
-                VertexAttributeDescriptor {
-                    name: Cow::from("Vertex_Color"),
-                    offset: VertexFormat::Float3.get_size() + VertexFormat::Float2.get_size(),
-                    format: VertexFormat::Float4,
-                    shader_location: 2,
-                },
-            ];
             pipeline_compiler.compile_pipeline(
                 render_resource_context,
                 &mut pipelines,
                 &mut shaders,
-                &MEGAUI_PIPELINE_HANDLE.typed(),
+                &self.source_pipeline_descriptor,
                 &PipelineSpecialization {
-                    vertex_buffer_descriptor: VertexBufferDescriptor {
-                        name: Cow::from("MegaUiVertex"),
-                        stride: attributes
-                            .iter()
-                            .fold(0, |acc, attribute| acc + attribute.format.get_size()),
-                        step_mode: InputStepMode::Vertex,
-                        attributes,
-                    },
+                    vertex_buffer_descriptor: vertex_buffer_descriptor(
+                        "MegaUiVertex",
+                        &[
+                            ("Vertex_Position", VertexFormat::Float3),
+                            ("Vertex_Uv", VertexFormat::Float2),
+                            ("Vertex_Color", VertexFormat::Float4),
+                            ("Vertex_WindowRect", VertexFormat::Float4),
+                            ("Vertex_CornerRadius", VertexFormat::Float),
+                        ],
+                    ),
                     index_format: IndexFormat::Uint16,
-                    sample_count: msaa.samples,
+                    sample_count,
                     ..PipelineSpecialization::default()
                 },
             )
@@ -393,147 +796,1075 @@ impl MegaUiNode {
         let layout = pipeline_descriptor.layout.as_ref().unwrap();
         let transform_bind_group =
             find_bind_group_by_binding_name(layout, MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME)
-                .unwrap();
+                .unwrap_or_else(|| {
+                    panic!(
+                        "The megaui pipeline is missing the `{}` binding",
+                        MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME
+                    )
+                });
         let texture_bind_group =
-            find_bind_group_by_binding_name(layout, MEGAUI_TEXTURE_RESOURCE_BINDING_NAME).unwrap();
+            find_bind_group_by_binding_name(layout, MEGAUI_TEXTURE_RESOURCE_BINDING_NAME)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "The megaui pipeline is missing the `{}` binding",
+                        MEGAUI_TEXTURE_RESOURCE_BINDING_NAME
+                    )
+                });
 
         self.pipeline_descriptor = Some(pipeline_descriptor_handle);
+        self.pipeline_sample_count = sample_count;
         self.transform_bind_group_descriptor = Some(transform_bind_group);
         self.texture_bind_group_descriptor = Some(texture_bind_group);
     }
 
-    fn init_transform_bind_group(
+    // The intermediate render target isn't multisampled.
+    fn ui_sample_count(&self) -> u32 {
+        match &self.render_target {
+            Some(render_target) => render_target.pass_descriptor.sample_count,
+            None => self.pass_descriptor.sample_count,
+        }
+    }
+
+    fn init_composite_pipeline(
         &mut self,
         render_context: &mut dyn RenderContext,
-        render_resource_bindings: &RenderResourceBindings,
+        resources: &Resources,
     ) {
-        if self.transform_bind_group.is_none() {
-            let transform_bindings = render_resource_bindings
-                .get(MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME)
-                .unwrap()
-                .clone();
-            let transform_bind_group = BindGroup::build()
-                .add_binding(0, transform_bindings)
-                .finish();
-            self.transform_bind_group = Some(transform_bind_group);
+        let sample_count = self.composite_pass_descriptor.sample_count;
+        if self.composite_pipeline_descriptor.is_some()
+            && self.composite_pipeline_sample_count == sample_count
+        {
+            return;
         }
-        render_context.resources().create_bind_group(
-            self.transform_bind_group_descriptor.as_ref().unwrap().id,
-            self.transform_bind_group.as_ref().unwrap(),
+
+        let mut pipelines = resources.get_mut::<Assets<PipelineDescriptor>>().unwrap();
+        let mut shaders = resources.get_mut::<Assets<Shader>>().unwrap();
+        let mut pipeline_compiler = resources.get_mut::<PipelineCompiler>().unwrap();
+        let pipeline_descriptor_handle = pipeline_compiler.compile_pipeline(
+            render_context.resources(),
+            &mut pipelines,
+            &mut shaders,
+            &MEGAUI_COMPOSITE_PIPELINE_HANDLE.typed(),
+            &PipelineSpecialization {
+                sample_count,
+                ..PipelineSpecialization::default()
+            },
         );
+
+        let pipeline_descriptor = pipelines.get(pipeline_descriptor_handle.clone()).unwrap();
+        let layout = pipeline_descriptor.layout.as_ref().unwrap();
+        let composite_bind_group =
+            find_bind_group_by_binding_name(layout, MEGAUI_COMPOSITE_TEXTURE_RESOURCE_BINDING_NAME)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "The megaui composite pipeline is missing the `{}` binding",
+                        MEGAUI_COMPOSITE_TEXTURE_RESOURCE_BINDING_NAME
+                    )
+                });
+
+        self.composite_pipeline_descriptor = Some(pipeline_descriptor_handle);
+        self.composite_pipeline_sample_count = sample_count;
+        self.composite_bind_group_descriptor = Some(composite_bind_group);
     }
 
-    fn process_asset_events(
+    // Creates, resizes or removes the intermediate render target according to
+    // `MegaUiSettings::resolution_scale` and the MSAA attachments.
+    fn process_render_target(
         &mut self,
         render_context: &mut dyn RenderContext,
-        megaui_context: &mut MegaUiContext,
-        asset_events: &Events<AssetEvent<Texture>>,
-        texture_assets: &Assets<Texture>,
+        resources: &Resources,
+        window_size: &WindowSize,
+        megaui_settings: &MegaUiSettings,
     ) {
-        let mut changed_assets: HashMap<Handle<Texture>, &Texture> = HashMap::new();
-        for event in self.event_reader.iter(asset_events) {
-            let handle = match event {
-                AssetEvent::Created { ref handle }
-                | AssetEvent::Modified { ref handle }
-                | AssetEvent::Removed { ref handle } => handle,
-            };
-            if !self.texture_resources.contains_key(handle) {
-                continue;
+        // The depth texture can't be attached to a scaled render target, as it's bigger.
+        let has_depth = self.pass_descriptor.depth_stencil_attachment.is_some();
+        let scaled = megaui_settings.resolution_scale < 1.0 && !has_depth;
+        // Megaui-owned multisampled attachments don't contain the scene, so the UI is resolved
+        // into a full-size render target, which is then composited onto the window.
+        let multisampled = !scaled
+            && self.pass_descriptor.sample_count > 1
+            && self.attachments.owned_sampled_color_attachment;
+        let use_render_target = scaled || multisampled;
+        if !use_render_target {
+            self.remove_render_target(render_context);
+            return;
+        }
+        self.init_composite_pipeline(render_context, resources);
+
+        let size = window_size.render_target_size(if scaled {
+            megaui_settings.resolution_scale
+        } else {
+            1.0
+        });
+        let filter = megaui_settings.upscale_filter;
+        if let Some(render_target) = &self.render_target {
+            if render_target.size == size && render_target.filter == filter {
+                render_context.resources().create_bind_group(
+                    self.composite_bind_group_descriptor.as_ref().unwrap().id,
+                    &render_target.bind_group,
+                );
+                // The sampled color attachment can be re-created, so the descriptor is updated.
+                let pass_descriptor =
+                    self.render_target_pass_descriptor(render_target.texture, multisampled);
+                self.render_target.as_mut().unwrap().pass_descriptor = pass_descriptor;
+                return;
             }
-            log::debug!("{:?}", event);
+        }
+        self.remove_render_target(render_context);
+        log::debug!(
+            "Creating a megaui render target: {}x{} ({:?})",
+            size.0,
+            size.1,
+            filter
+        );
 
-            match event {
-                AssetEvent::Created { .. } => {
-                    // Don't have to do anything really, since we track uninitialized textures
-                    // via `MegaUiContext::set_megaui_texture` and `Self::init_textures`.
-                }
-                AssetEvent::Modified { ref handle } => {
-                    if let Some(asset) = texture_assets.get(handle) {
-                        changed_assets.insert(handle.clone(), asset);
-                    }
-                }
-                AssetEvent::Removed { ref handle } => {
-                    megaui_context.remove_texture(handle);
-                    self.remove_texture(render_context, handle);
-                    // If an asset was modified and removed in the same update, ignore the modification.
-                    changed_assets.remove(&handle);
-                }
-            }
+        let render_resource_context = render_context.resources_mut();
+        let texture = render_resource_context.create_texture(TextureDescriptor {
+            size: Extent3d::new(size.0, size.1, 1),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::default(),
+            usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED,
+        });
+        let sampler = render_resource_context.create_sampler(&SamplerDescriptor {
+            mag_filter: filter,
+            min_filter: filter,
+            ..SamplerDescriptor::default()
+        });
+        let bind_group = BindGroup::build()
+            .add_binding(0, RenderResourceBinding::Texture(texture))
+            .add_binding(1, RenderResourceBinding::Sampler(sampler))
+            .finish();
+        render_resource_context.create_bind_group(
+            self.composite_bind_group_descriptor.as_ref().unwrap().id,
+            &bind_group,
+        );
+
+        let pass_descriptor = self.render_target_pass_descriptor(texture, multisampled);
+        self.render_target = Some(RenderTarget {
+            size,
+            filter,
+            texture,
+            sampler,
+            bind_group,
+            pass_descriptor,
+        });
+    }
+
+    // The UI is either rendered to the render target directly, or resolved into it from
+    // the multisampled color attachment.
+    fn render_target_pass_descriptor(
+        &self,
+        texture: TextureId,
+        multisampled: bool,
+    ) -> PassDescriptor {
+        let ops = Operations {
+            load: LoadOp::Clear(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+            store: true,
+        };
+        if !multisampled {
+            return PassDescriptor {
+                color_attachments: vec![RenderPassColorAttachmentDescriptor {
+                    attachment: TextureAttachment::Id(texture),
+                    resolve_target: None,
+                    ops,
+                }],
+                depth_stencil_attachment: None,
+                sample_count: 1,
+            };
         }
-        for (texture_handle, texture) in changed_assets {
-            self.update_texture(render_context, texture, texture_handle);
+        PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachmentDescriptor {
+                attachment: self.pass_descriptor.color_attachments[0].attachment.clone(),
+                resolve_target: Some(TextureAttachment::Id(texture)),
+                ops,
+            }],
+            depth_stencil_attachment: self.pass_descriptor.depth_stencil_attachment.clone(),
+            sample_count: self.pass_descriptor.sample_count,
         }
     }
 
-    fn remove_unused_textures(
-        &mut self,
-        render_context: &mut dyn RenderContext,
-        megaui_context: &MegaUiContext,
-    ) {
-        let texture_handles = megaui_context
-            .megaui_textures
-            .values()
-            .collect::<HashSet<_>>();
-        let mut textures_to_remove = Vec::new();
-
-        for texture_handle in self.texture_resources.keys() {
-            if !texture_handles.contains(texture_handle)
-                && megaui_context.font_texture != *texture_handle
-            {
-                textures_to_remove.push(texture_handle.clone_weak());
-            }
-        }
-        for texture_to_remove in textures_to_remove {
-            self.remove_texture(render_context, &texture_to_remove);
+    fn remove_render_target(&mut self, render_context: &mut dyn RenderContext) {
+        if let Some(render_target) = self.render_target.take() {
+            let render_resource_context = render_context.resources_mut();
+            render_resource_context.remove_texture(render_target.texture);
+            render_resource_context.remove_sampler(render_target.sampler);
         }
     }
 
-    fn init_textures(
+    fn init_blur_pipeline(
         &mut self,
         render_context: &mut dyn RenderContext,
-        megaui_context: &MegaUiContext,
-        texture_assets: &Assets<Texture>,
+        resources: &Resources,
     ) {
-        self.create_texture(render_context, texture_assets, self.font_texture.clone());
-
-        for texture in megaui_context.megaui_textures.values() {
-            self.create_texture(render_context, texture_assets, texture.clone_weak());
+        let sample_count = self.ui_sample_count();
+        if self.blur_pipeline_descriptor.is_some()
+            && self.blur_pipeline_sample_count == sample_count
+        {
+            return;
         }
+
+        let mut pipelines = resources.get_mut::<Assets<PipelineDescriptor>>().unwrap();
+        let mut shaders = resources.get_mut::<Assets<Shader>>().unwrap();
+        let mut pipeline_compiler = resources.get_mut::<PipelineCompiler>().unwrap();
+        let pipeline_descriptor_handle = pipeline_compiler.compile_pipeline(
+            render_context.resources(),
+            &mut pipelines,
+            &mut shaders,
+            &MEGAUI_BLUR_PIPELINE_HANDLE.typed(),
+            &PipelineSpecialization {
+                vertex_buffer_descriptor: vertex_buffer_descriptor(
+                    "MegaUiBlurVertex",
+                    &[
+                        ("Vertex_Position", VertexFormat::Float3),
+                        ("Vertex_Color", VertexFormat::Float4),
+                        ("Vertex_BlurRadius", VertexFormat::Float2),
+                        ("Vertex_Local", VertexFormat::Float2),
+                        ("Vertex_Shape", VertexFormat::Float3),
+                    ],
+                ),
+                index_format: IndexFormat::Uint16,
+                sample_count,
+                ..PipelineSpecialization::default()
+            },
+        );
+
+        let pipeline_descriptor = pipelines.get(pipeline_descriptor_handle.clone()).unwrap();
+        let layout = pipeline_descriptor.layout.as_ref().unwrap();
+        let binding = |name: &str| {
+            find_bind_group_by_binding_name(layout, name).unwrap_or_else(|| {
+                panic!("The megaui blur pipeline is missing the `{}` binding", name)
+            })
+        };
+
+        self.blur_transform_bind_group_descriptor =
+            Some(binding(MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME));
+        self.blur_texture_bind_group_descriptor =
+            Some(binding(MEGAUI_BACKGROUND_TEXTURE_RESOURCE_BINDING_NAME));
+        self.blur_pipeline_descriptor = Some(pipeline_descriptor_handle);
+        self.blur_pipeline_sample_count = sample_count;
     }
 
-    fn update_texture(
+    // Copies the scene color and builds the background geometry for the window rects. Only
+    // the primary window is supported, as secondary windows render directly to swap chains.
+    fn prepare_background_blur(
         &mut self,
         render_context: &mut dyn RenderContext,
-        texture_asset: &Texture,
-        texture_handle: Handle<Texture>,
+        resources: &Resources,
+        input: &ResourceSlots,
+        window_size: &WindowSize,
+        megaui_settings: &MegaUiSettings,
+        window_rects: &[megaui::Rect],
     ) {
-        let texture_resource = match self.texture_resources.get(&texture_handle) {
-            Some(texture_resource) => texture_resource,
-            None => return,
+        let background_blur = match megaui_settings.background_blur {
+            Some(background_blur) if self.window_id.is_primary() => background_blur,
+            _ => {
+                self.remove_background_blur(render_context);
+                return;
+            }
         };
-        log::debug!("Updating a texture: ${:?}", texture_handle);
-
-        let texture_descriptor: TextureDescriptor = texture_asset.into();
+        let blur_source = self.check_blur_source(input).and_then(|color_attachment| {
+            let size = self.blur_size(resources, window_size)?;
+            Ok(size.map(|size| (color_attachment, size)))
+        });
+        let (color_attachment, size) = match blur_source {
+            Ok(Some(blur_source)) => blur_source,
+            // The window is being resized, the sizes match again in the next frames.
+            Ok(None) => return,
+            Err(err) => {
+                // The warning is logged once, not every frame.
+                if !self.blur_warning_logged {
+                    log::warn!(
+                        "`MegaUiSettings::background_blur` is enabled, but window backgrounds \
+                         can't be blurred: {}",
+                        err
+                    );
+                    self.blur_warning_logged = true;
+                }
+                self.remove_background_blur(render_context);
+                return;
+            }
+        };
+        self.blur_warning_logged = false;
+
+        self.init_blur_pipeline(render_context, resources);
+        let blur_transform_bind_group_descriptor =
+            self.blur_transform_bind_group_descriptor.as_ref().unwrap();
+        let blur_transform_bind_group = window_uniforms_bind_group(
+            blur_transform_bind_group_descriptor,
+            &resources.get::<RenderResourceBindings>().unwrap(),
+            self.window_id,
+        );
+        render_context.resources().create_bind_group(
+            blur_transform_bind_group_descriptor.id,
+            &blur_transform_bind_group,
+        );
+        self.blur_transform_bind_group = Some(blur_transform_bind_group);
+        self.init_background_texture(render_context, size);
 
-        if texture_descriptor != texture_resource.descriptor {
-            log::debug!(
-                "Removing an updated texture for it to be re-created later: {:?}",
-                texture_handle
-            );
-            // If a texture descriptor is updated, we'll re-create the texture in `init_textures`.
-            self.remove_texture(render_context, &texture_handle);
+        let background = self.background_blur.as_mut().unwrap();
+        if let Some(vertex_buffer) = background.vertex_buffer.take() {
+            render_context.resources().remove_buffer(vertex_buffer);
+        }
+        if let Some(index_buffer) = background.index_buffer.take() {
+            render_context.resources().remove_buffer(index_buffer);
+        }
+        background.indices_count = 0;
+        if window_rects.is_empty() {
             return;
         }
-        Self::copy_texture(render_context, &texture_resource, texture_asset);
-    }
 
-    fn create_texture(
-        &mut self,
+        render_context.copy_texture_to_texture(
+            color_attachment,
+            [0, 0, 0],
+            0,
+            background.texture,
+            [0, 0, 0],
+            0,
+            Extent3d::new(size.0, size.1, 1),
+        );
+
+        // Texture coordinates are derived from projected positions in the shader, the radius
+        // is converted from UI units (not taking `MegaUiSettings::transforms` into account).
+        let blur_radius = [
+            background_blur.radius * megaui_settings.scale_factor as f32 / window_size.width(),
+            background_blur.radius * megaui_settings.scale_factor as f32 / window_size.height(),
+        ];
+        // Backgrounds get the rounded corners of their windows.
+        let corner_radius = megaui_settings
+            .window_decorations
+            .map_or(0.0, |window_decorations| window_decorations.corner_radius);
+        let mut vertex_buffer = Vec::<u8>::new();
+        let mut index_buffer = Vec::<u16>::new();
+        for (i, rect) in window_rects.iter().enumerate() {
+            let (center, half_size, corner_radius) = rounded_rect(*rect, corner_radius);
+            let shape = [half_size[0], half_size[1], corner_radius];
+            let corners = [
+                [rect.x, rect.y],
+                [rect.x + rect.w, rect.y],
+                [rect.x + rect.w, rect.y + rect.h],
+                [rect.x, rect.y + rect.h],
+            ];
+            for [x, y] in &corners {
+                vertex_buffer.extend_from_slice([*x, *y, 0.0].as_bytes());
+                vertex_buffer.extend_from_slice(background_blur.tint.as_bytes());
+                vertex_buffer.extend_from_slice(blur_radius.as_bytes());
+                vertex_buffer.extend_from_slice([x - center[0], y - center[1]].as_bytes());
+                vertex_buffer.extend_from_slice(shape.as_bytes());
+            }
+            let offset = (i * corners.len()) as u16;
+            index_buffer.extend_from_slice(&[
+                offset,
+                offset + 1,
+                offset + 2,
+                offset,
+                offset + 2,
+                offset + 3,
+            ]);
+        }
+
+        let render_resource_context = render_context.resources();
+        background.vertex_buffer = Some(render_resource_context.create_buffer_with_data(
+            BufferInfo {
+                buffer_usage: BufferUsage::VERTEX,
+                ..Default::default()
+            },
+            &vertex_buffer,
+        ));
+        background.index_buffer = Some(render_resource_context.create_buffer_with_data(
+            BufferInfo {
+                buffer_usage: BufferUsage::INDEX,
+                ..Default::default()
+            },
+            index_buffer.as_slice().as_bytes(),
+        ));
+        background.indices_count = index_buffer.len() as u32;
+    }
+
+    // Checks that the color source can be copied into the background texture.
+    fn check_blur_source(&self, input: &ResourceSlots) -> Result<TextureId, String> {
+        if self.attachments.color_source_is_swap_chain {
+            return Err(
+                "the color source is a swap chain texture, which can't be copied \
+                 (see `MegaUiRenderGraphConfig::color_source`)"
+                    .into(),
+            );
+        }
+        input
+            .get(Self::COLOR_ATTACHMENT_INPUT_INDEX)
+            .and_then(|resource| resource.get_texture())
+            .ok_or_else(|| "the color source doesn't provide a texture".to_string())
+    }
+
+    // Window-sized textures are re-created on resizing before the megaui pass runs, so their
+    // size matches the current physical size of the window. Returns `None` while the UI size
+    // lags behind the window size.
+    fn blur_size(
+        &self,
+        resources: &Resources,
+        window_size: &WindowSize,
+    ) -> Result<Option<(u32, u32)>, String> {
+        let windows = resources.get::<Windows>().unwrap();
+        let window = windows
+            .get(self.window_id)
+            .ok_or_else(|| "the window doesn't exist".to_string())?;
+        let size = (window.physical_width(), window.physical_height());
+        let expected_size = (
+            window_size.physical_width as u32,
+            window_size.physical_height as u32,
+        );
+        if size != expected_size || size.0 == 0 || size.1 == 0 {
+            return Ok(None);
+        }
+        Ok(Some(size))
+    }
+
+    fn init_background_texture(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        size: (u32, u32),
+    ) {
+        if let Some(background_blur) = &self.background_blur {
+            if background_blur.size == size {
+                render_context.resources().create_bind_group(
+                    self.blur_texture_bind_group_descriptor.as_ref().unwrap().id,
+                    &background_blur.bind_group,
+                );
+                return;
+            }
+        }
+        self.remove_background_blur(render_context);
+
+        let render_resource_context = render_context.resources_mut();
+        let texture = render_resource_context.create_texture(TextureDescriptor {
+            size: Extent3d::new(size.0, size.1, 1),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::default(),
+            usage: TextureUsage::COPY_DST | TextureUsage::SAMPLED,
+        });
+        let sampler = render_resource_context.create_sampler(&SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..SamplerDescriptor::default()
+        });
+        let bind_group = BindGroup::build()
+            .add_binding(0, RenderResourceBinding::Texture(texture))
+            .add_binding(1, RenderResourceBinding::Sampler(sampler))
+            .finish();
+        render_resource_context.create_bind_group(
+            self.blur_texture_bind_group_descriptor.as_ref().unwrap().id,
+            &bind_group,
+        );
+
+        self.background_blur = Some(BackgroundBlur {
+            size,
+            texture,
+            sampler,
+            bind_group,
+            vertex_buffer: None,
+            index_buffer: None,
+            indices_count: 0,
+        });
+    }
+
+    fn remove_background_blur(&mut self, render_context: &mut dyn RenderContext) {
+        if let Some(background_blur) = self.background_blur.take() {
+            let render_resource_context = render_context.resources_mut();
+            render_resource_context.remove_texture(background_blur.texture);
+            render_resource_context.remove_sampler(background_blur.sampler);
+            if let Some(vertex_buffer) = background_blur.vertex_buffer {
+                render_resource_context.remove_buffer(vertex_buffer);
+            }
+            if let Some(index_buffer) = background_blur.index_buffer {
+                render_resource_context.remove_buffer(index_buffer);
+            }
+        }
+    }
+
+    fn init_decoration_pipelines(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        resources: &Resources,
+    ) {
+        let sample_count = self.ui_sample_count();
+        if self.shadow_pipeline_descriptor.is_some()
+            && self.decoration_pipeline_sample_count == sample_count
+        {
+            return;
+        }
+
+        let mut pipelines = resources.get_mut::<Assets<PipelineDescriptor>>().unwrap();
+        let mut shaders = resources.get_mut::<Assets<Shader>>().unwrap();
+        let mut pipeline_compiler = resources.get_mut::<PipelineCompiler>().unwrap();
+        let shadow_pipeline_descriptor = pipeline_compiler.compile_pipeline(
+            render_context.resources(),
+            &mut pipelines,
+            &mut shaders,
+            &MEGAUI_SHADOW_PIPELINE_HANDLE.typed(),
+            &PipelineSpecialization {
+                vertex_buffer_descriptor: vertex_buffer_descriptor(
+                    "MegaUiDecorationVertex",
+                    &[
+                        ("Vertex_Position", VertexFormat::Float3),
+                        ("Vertex_Local", VertexFormat::Float2),
+                        ("Vertex_Color", VertexFormat::Float4),
+                        ("Vertex_Shape", VertexFormat::Float4),
+                    ],
+                ),
+                index_format: IndexFormat::Uint32,
+                sample_count,
+                ..PipelineSpecialization::default()
+            },
+        );
+
+        let pipeline_descriptor = pipelines.get(shadow_pipeline_descriptor.clone()).unwrap();
+        let layout = pipeline_descriptor.layout.as_ref().unwrap();
+        let transform_bind_group =
+            find_bind_group_by_binding_name(layout, MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "The megaui shadow pipeline is missing the `{}` binding",
+                        MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME
+                    )
+                });
+
+        self.shadow_pipeline_descriptor = Some(shadow_pipeline_descriptor);
+        self.decoration_pipeline_sample_count = sample_count;
+        self.decoration_transform_bind_group_descriptor = Some(transform_bind_group);
+    }
+
+    // Builds shadows of the window rects.
+    fn prepare_window_decorations(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        resources: &Resources,
+        megaui_settings: &MegaUiSettings,
+        window_rects: &[megaui::Rect],
+    ) {
+        let old_window_decorations = std::mem::take(&mut self.window_decorations);
+        if let Some(vertex_buffer) = old_window_decorations.vertex_buffer {
+            render_context.resources().remove_buffer(vertex_buffer);
+        }
+        if let Some(index_buffer) = old_window_decorations.index_buffer {
+            render_context.resources().remove_buffer(index_buffer);
+        }
+
+        let window_decorations = match megaui_settings.window_decorations {
+            Some(window_decorations) => window_decorations,
+            None => return,
+        };
+        if window_rects.is_empty() || !window_decorations.has_shadows() {
+            return;
+        }
+
+        self.init_decoration_pipelines(render_context, resources);
+        let decoration_transform_bind_group_descriptor = self
+            .decoration_transform_bind_group_descriptor
+            .as_ref()
+            .unwrap();
+        let decoration_transform_bind_group = window_uniforms_bind_group(
+            decoration_transform_bind_group_descriptor,
+            &resources.get::<RenderResourceBindings>().unwrap(),
+            self.window_id,
+        );
+        render_context.resources().create_bind_group(
+            decoration_transform_bind_group_descriptor.id,
+            &decoration_transform_bind_group,
+        );
+        self.decoration_transform_bind_group = Some(decoration_transform_bind_group);
+
+        let mut vertex_buffer = Vec::<u8>::new();
+        let mut index_buffer = Vec::<u32>::new();
+        let [offset_x, offset_y] = window_decorations.shadow_offset;
+        // The decoration shaders don't declare the color transform, so the opacity is applied
+        // to shadow colors here.
+        let mut shadow_color = window_decorations.shadow_color;
+        shadow_color[3] *= megaui_settings.opacity();
+        let blur = window_decorations.shadow_blur.max(0.0);
+        for rect in window_rects {
+            let shadow = megaui::Rect::new(rect.x + offset_x, rect.y + offset_y, rect.w, rect.h);
+            push_decoration_quad(
+                &mut vertex_buffer,
+                &mut index_buffer,
+                shadow,
+                blur,
+                window_decorations.corner_radius,
+                shadow_color,
+            );
+        }
+
+        let render_resource_context = render_context.resources();
+        self.window_decorations = WindowDecorations {
+            vertex_buffer: Some(render_resource_context.create_buffer_with_data(
+                BufferInfo {
+                    buffer_usage: BufferUsage::VERTEX,
+                    ..Default::default()
+                },
+                &vertex_buffer,
+            )),
+            index_buffer: Some(render_resource_context.create_buffer_with_data(
+                BufferInfo {
+                    buffer_usage: BufferUsage::INDEX,
+                    ..Default::default()
+                },
+                index_buffer.as_slice().as_bytes(),
+            )),
+            shadow_indices_count: index_buffer.len() as u32,
+        };
+    }
+
+    fn set_decoration_buffers(&self, render_pass: &mut dyn RenderPass) {
+        render_pass.set_vertex_buffer(0, self.window_decorations.vertex_buffer.unwrap(), 0);
+        render_pass.set_index_buffer(self.window_decorations.index_buffer.unwrap(), 0);
+        render_pass.set_bind_group(
+            0,
+            self.decoration_transform_bind_group_descriptor
+                .as_ref()
+                .unwrap()
+                .id,
+            self.decoration_transform_bind_group.as_ref().unwrap().id,
+            None,
+        );
+    }
+
+    fn background_blur_indices_count(&self) -> u32 {
+        self.background_blur
+            .as_ref()
+            .map_or(0, |background_blur| background_blur.indices_count)
+    }
+
+    // Replaces the source pipeline shaders with the hot-reloaded ones when they get loaded
+    // or modified, the pipeline is recompiled in `Self::init_pipeline` afterwards.
+    fn process_shader_events(&mut self, resources: &Resources) {
+        let hot_reloaded_shaders = match resources.get::<HotReloadedShaders>() {
+            Some(hot_reloaded_shaders) => hot_reloaded_shaders,
+            None => return,
+        };
+        let shader_events = resources.get::<Events<AssetEvent<Shader>>>().unwrap();
+
+        let mut shaders_changed = false;
+        for event in self.shader_event_reader.iter(&shader_events) {
+            match event {
+                AssetEvent::Created { ref handle } | AssetEvent::Modified { ref handle } => {
+                    if *handle == hot_reloaded_shaders.vertex
+                        || *handle == hot_reloaded_shaders.fragment
+                    {
+                        shaders_changed = true;
+                    }
+                }
+                AssetEvent::Removed { .. } => {}
+            }
+        }
+        if !shaders_changed {
+            return;
+        }
+
+        let mut pipelines = resources.get_mut::<Assets<PipelineDescriptor>>().unwrap();
+        let mut shaders = resources.get_mut::<Assets<Shader>>().unwrap();
+        let (vertex_shader, fragment_shader) = match (
+            shaders.get(&hot_reloaded_shaders.vertex),
+            shaders.get(&hot_reloaded_shaders.fragment),
+        ) {
+            (Some(vertex_shader), Some(fragment_shader)) => {
+                (vertex_shader.clone(), fragment_shader.clone())
+            }
+            // Wait for both shaders to get loaded.
+            _ => return,
+        };
+
+        let embedded_pipeline_descriptor_handle = MEGAUI_PIPELINE_HANDLE.typed();
+        if self.source_pipeline_descriptor != embedded_pipeline_descriptor_handle {
+            pipelines.remove(&self.source_pipeline_descriptor);
+        }
+        self.pipeline_descriptor = None;
+
+        if let Err(err) = validate_shader(&vertex_shader).and(validate_shader(&fragment_shader)) {
+            log::error!(
+                "Failed to compile megaui shaders, falling back to the embedded ones: {}",
+                err
+            );
+            self.source_pipeline_descriptor = embedded_pipeline_descriptor_handle;
+            return;
+        }
+        log::debug!("Recompiling the megaui pipeline with hot-reloaded shaders");
+
+        let mut pipeline_descriptor = pipelines
+            .get(&embedded_pipeline_descriptor_handle)
+            .unwrap()
+            .clone();
+        // Specialized shaders are cached by their handles, so we add copies of the sources
+        // for them to get recompiled.
+        pipeline_descriptor.shader_stages = ShaderStages {
+            vertex: shaders.add(vertex_shader),
+            fragment: Some(shaders.add(fragment_shader)),
+        };
+        self.source_pipeline_descriptor = pipelines.add(pipeline_descriptor);
+    }
+
+    fn init_transform_bind_group(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        render_resource_bindings: &RenderResourceBindings,
+    ) {
+        // The bind group has to match the descriptor, which changes with hot reloaded shaders.
+        let transform_bind_group_descriptor =
+            self.transform_bind_group_descriptor.as_ref().unwrap();
+        let transform_bind_group = window_uniforms_bind_group(
+            transform_bind_group_descriptor,
+            render_resource_bindings,
+            self.window_id,
+        );
+        render_context
+            .resources()
+            .create_bind_group(transform_bind_group_descriptor.id, &transform_bind_group);
+        self.transform_bind_group = Some(transform_bind_group);
+    }
+
+    fn process_asset_events(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        layers: &mut WindowLayers,
+        asset_events: &Events<AssetEvent<Texture>>,
+        texture_assets: &Assets<Texture>,
+    ) {
+        let mut changed_assets: HashMap<Handle<Texture>, &Texture> = HashMap::new();
+        for event in self.event_reader.iter(asset_events) {
+            let handle = match event {
+                AssetEvent::Created { ref handle }
+                | AssetEvent::Modified { ref handle }
+                | AssetEvent::Removed { ref handle } => handle,
+            };
+            let is_invalid = self
+                .invalid_textures
+                .values()
+                .any(|(invalid_handle, _)| invalid_handle == handle);
+            if !is_invalid
+                && !self
+                    .texture_resources
+                    .values()
+                    .any(|texture_resource| texture_resource.handle == *handle)
+            {
+                continue;
+            }
+            log::debug!("{:?}", event);
+
+            match event {
+                AssetEvent::Created { .. } => {
+                    // Don't have to do anything really, since we track uninitialized textures
+                    // via `MegaUiContext::set_megaui_texture` and `Self::init_textures`.
+                }
+                AssetEvent::Modified { ref handle } => {
+                    self.invalid_textures
+                        .retain(|_, (invalid_handle, _)| invalid_handle != handle);
+                    if let Some(asset) = texture_assets.get(handle) {
+                        changed_assets.insert(handle.clone(), asset);
+                    }
+                }
+                AssetEvent::Removed { ref handle } => {
+                    self.invalid_textures
+                        .retain(|_, (invalid_handle, _)| invalid_handle != handle);
+                    for (_, megaui_context) in layers.iter_mut() {
+                        megaui_context.remove_texture(handle);
+                    }
+                    for texture_key in self.texture_keys_by_handle(handle) {
+                        self.remove_texture(render_context, &texture_key);
+                    }
+                    // If an asset was modified and removed in the same update, ignore the modification.
+                    changed_assets.remove(&handle);
+                }
+            }
+        }
+        for (texture_handle, texture) in changed_assets {
+            for texture_key in self.texture_keys_by_handle(&texture_handle) {
+                let megaui_context = match layer_context(layers, &texture_key) {
+                    Some(megaui_context) => megaui_context,
+                    None => continue,
+                };
+                if let Some(megaui_texture) = megaui_context.megaui_texture(texture_key.texture_id)
+                {
+                    self.update_texture(
+                        render_context,
+                        texture,
+                        &texture_key,
+                        megaui_texture,
+                        megaui_context.dirty_texture_rects(texture_key.texture_id),
+                    );
+                }
+            }
+        }
+    }
+
+    fn texture_keys_by_handle(&self, texture_handle: &Handle<Texture>) -> Vec<TextureKey> {
+        self.texture_resources
+            .iter()
+            .filter(|(_, texture_resource)| texture_resource.handle == *texture_handle)
+            .map(|(texture_key, _)| texture_key.clone())
+            .collect()
+    }
+
+    fn remove_unused_textures(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        layers: &WindowLayers,
+    ) {
+        let mut textures_to_remove = Vec::new();
+
+        for (texture_key, texture_resource) in &self.texture_resources {
+            // A texture is also re-created if it was registered again with different params.
+            let is_used = layer_megaui_texture(layers, texture_key).map_or(false, |texture| {
+                texture.handle == texture_resource.handle
+                    && texture.generation == texture_resource.generation
+            });
+            if !is_used {
+                textures_to_remove.push(texture_key.clone());
+            }
+        }
+        for texture_to_remove in textures_to_remove {
+            self.remove_texture(render_context, &texture_to_remove);
+        }
+        self.evicted_textures.retain(|texture_key, generation| {
+            layer_megaui_texture(layers, texture_key)
+                .map_or(false, |texture| texture.generation == *generation)
+        });
+    }
+
+    // Creates registered textures and marks the drawn ones as used.
+    fn init_textures(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        layers: &WindowLayers,
+        drawn_textures: &HashSet<TextureKey>,
+        texture_assets: &Assets<Texture>,
+    ) {
+        let mut textures = layers
+            .iter()
+            .flat_map(|(layer, megaui_context)| {
+                std::iter::once((None, &megaui_context.font_texture))
+                    .chain(
+                        megaui_context
+                            .megaui_textures
+                            .iter()
+                            .map(|(texture_id, texture)| (Some(*texture_id), texture)),
+                    )
+                    .map(move |(texture_id, texture)| {
+                        let texture_key = TextureKey {
+                            layer: layer.clone(),
+                            texture_id,
+                        };
+                        (texture_key, texture)
+                    })
+            })
+            .collect::<Vec<_>>();
+        self.queued_textures.clear();
+        // Textures are created in a stable order, drawn ones go first, so that they get into
+        // the upload budget (see `MegaUiSettings::texture_upload_budget`).
+        textures.sort_by_cached_key(|(texture_key, _)| {
+            (!drawn_textures.contains(texture_key), texture_key.clone())
+        });
+
+        for (texture_key, texture) in textures {
+            let is_drawn = drawn_textures.contains(&texture_key);
+            if !is_drawn && self.evicted_textures.get(&texture_key) == Some(&texture.generation) {
+                continue;
+            }
+            self.evicted_textures.remove(&texture_key);
+
+            self.create_texture(render_context, texture_assets, &texture_key, texture);
+            if let Some(texture_resource) = self.texture_resources.get_mut(&texture_key) {
+                if is_drawn {
+                    texture_resource.last_drawn_frame = self.frame;
+                }
+            }
+        }
+    }
+
+    // Evicts the least recently drawn textures until they fit into
+    // `MegaUiSettings::texture_memory_budget`. Textures drawn during the frame are kept.
+    fn evict_textures(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        megaui_settings: &MegaUiSettings,
+    ) {
+        let texture_memory_budget = match megaui_settings.texture_memory_budget {
+            Some(texture_memory_budget) => texture_memory_budget,
+            None => return,
+        };
+        let mut texture_bytes = self.texture_bytes();
+        if texture_bytes <= texture_memory_budget {
+            return;
+        }
+
+        let mut textures_to_evict = self
+            .texture_resources
+            .iter()
+            .filter(|(_, texture_resource)| texture_resource.last_drawn_frame < self.frame)
+            .map(|(texture_key, texture_resource)| {
+                (texture_resource.last_drawn_frame, texture_key.clone())
+            })
+            .collect::<Vec<_>>();
+        textures_to_evict.sort_by_key(|(last_drawn_frame, _)| *last_drawn_frame);
+
+        for (_, texture_key) in textures_to_evict {
+            if texture_bytes <= texture_memory_budget {
+                break;
+            }
+            let texture_resource = &self.texture_resources[&texture_key];
+            log::debug!("Evicting a texture: {:?}", texture_resource.handle);
+            texture_bytes -= texture_resource.size_bytes;
+            self.evicted_textures
+                .insert(texture_key.clone(), texture_resource.generation);
+            self.remove_texture(render_context, &texture_key);
+            self.stats.texture_evictions += 1;
+        }
+    }
+
+    fn texture_bytes(&self) -> usize {
+        self.texture_resources
+            .values()
+            .map(|texture_resource| texture_resource.size_bytes)
+            .sum()
+    }
+
+    // Converts a texture region (if it's set) from pixels to the UV space.
+    fn texture_uv_rect(
+        &self,
+        megaui_context: &MegaUiContext,
+        texture_key: &TextureKey,
+    ) -> Option<megaui::Rect> {
+        let region = megaui_context
+            .megaui_texture(texture_key.texture_id)?
+            .params
+            .region?;
+        let size = self.texture_resources.get(texture_key)?.descriptor.size;
+        Some(megaui::Rect::new(
+            region.x / size.width as f32,
+            region.y / size.height as f32,
+            region.w / size.width as f32,
+            region.h / size.height as f32,
+        ))
+    }
+
+    fn update_texture(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        texture_asset: &Texture,
+        texture_key: &TextureKey,
+        megaui_texture: &MegaUiTexture,
+        dirty_rects: &[megaui::Rect],
+    ) {
+        let texture_resource = match self.texture_resources.get(texture_key) {
+            Some(texture_resource) => texture_resource,
+            None => return,
+        };
+        log::debug!("Updating a texture: ${:?}", texture_resource.handle);
+
+        // Only the marked regions are uploaded (see `MegaUiContext::mark_megaui_texture_dirty`),
+        // unless mip levels have to be regenerated.
+        let regions = if megaui_texture.params.mipmaps {
+            Vec::new()
+        } else {
+            dirty_rects
+                .iter()
+                .filter_map(|rect| {
+                    TextureRegion::from_rect(
+                        *rect,
+                        texture_asset.size.width,
+                        texture_asset.size.height,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let texture_data = if regions.is_empty() {
+            prepare_texture_data(
+                texture_asset,
+                &megaui_texture.params,
+                self.premultiplied_alpha,
+            )
+            .map(|texture_data| vec![(None, texture_data)])
+        } else {
+            regions
+                .into_iter()
+                .map(|region| {
+                    prepare_texture_region_data(
+                        texture_asset,
+                        &megaui_texture.params,
+                        self.premultiplied_alpha,
+                        region,
+                    )
+                    .map(|texture_data| (Some(region), texture_data))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let texture_data = match texture_data {
+            Ok(texture_data) => texture_data,
+            Err(err) => {
+                self.invalidate_texture(render_context, texture_key, megaui_texture, err);
+                return;
+            }
+        };
+
+        if texture_data
+            .iter()
+            .any(|(_, texture_data)| texture_data.descriptor != texture_resource.descriptor)
+        {
+            log::debug!(
+                "Removing an updated texture for it to be re-created later: {:?}",
+                texture_resource.handle
+            );
+            // If a texture descriptor is updated, we'll re-create the texture in `init_textures`.
+            self.remove_texture(render_context, texture_key);
+            return;
+        }
+        let mut upload_bytes = 0;
+        for (region, texture_data) in &texture_data {
+            upload_bytes += match region {
+                Some(region) => Self::copy_texture_region(
+                    render_context,
+                    texture_resource.texture,
+                    texture_data.descriptor.format,
+                    0,
+                    *region,
+                    &texture_data.data,
+                ),
+                None => Self::copy_texture(render_context, &texture_resource, texture_data),
+            };
+        }
+        self.record_texture_upload(upload_bytes);
+    }
+
+    fn invalidate_texture(
+        &mut self,
+        render_context: &mut dyn RenderContext,
+        texture_key: &TextureKey,
+        megaui_texture: &MegaUiTexture,
+        err: TextureDataError,
+    ) {
+        log::error!(
+            "Megaui texture {:?} can't be displayed: {:?}",
+            megaui_texture.handle,
+            err
+        );
+        self.remove_texture(render_context, texture_key);
+        self.invalid_textures.insert(
+            texture_key.clone(),
+            (
+                megaui_texture.handle.clone_weak(),
+                megaui_texture.generation,
+            ),
+        );
+    }
+
+    fn create_texture(
+        &mut self,
         render_context: &mut dyn RenderContext,
         texture_assets: &Assets<Texture>,
-        texture_handle: Handle<Texture>,
+        texture_key: &TextureKey,
+        megaui_texture: &MegaUiTexture,
     ) {
-        if let Some(texture_resource) = self.texture_resources.get(&texture_handle) {
+        if let Some(texture_resource) = self.texture_resources.get(texture_key) {
             // bevy_webgl2 seems to clean bind groups each frame.
             render_context.resources().create_bind_group(
                 self.texture_bind_group_descriptor.as_ref().unwrap().id,
@@ -542,19 +1873,53 @@ impl MegaUiNode {
             return;
         }
 
-        // If a texture is still loading, we skip it.
-        let texture_asset = match texture_assets.get(texture_handle.clone()) {
+        if let Some((_, generation)) = self.invalid_textures.get(texture_key) {
+            if *generation == megaui_texture.generation {
+                return;
+            }
+        }
+
+        // If a texture is still loading (or has failed to load), we skip it.
+        let texture_asset = match texture_assets.get(&megaui_texture.handle) {
             Some(texture_asset) => texture_asset,
             None => return,
         };
 
-        log::debug!("Creating a texture: ${:?}", texture_handle);
+        // The texture stays queued until the next frame, it's drawn as a placeholder meanwhile.
+        if self.is_upload_budget_exhausted() {
+            self.queued_textures.insert(texture_key.clone());
+            return;
+        }
+
+        log::debug!("Creating a texture: ${:?}", megaui_texture.handle);
+
+        let texture_data = match prepare_texture_data(
+            texture_asset,
+            &megaui_texture.params,
+            self.premultiplied_alpha,
+        ) {
+            Ok(texture_data) => texture_data,
+            Err(err) => {
+                self.invalidate_texture(render_context, texture_key, megaui_texture, err);
+                return;
+            }
+        };
+        self.invalid_textures.remove(texture_key);
 
         let render_resource_context = render_context.resources();
 
-        let texture_descriptor: TextureDescriptor = texture_asset.into();
+        let texture_descriptor = texture_data.descriptor;
         let texture = render_resource_context.create_texture(texture_descriptor);
-        let sampler = render_resource_context.create_sampler(&texture_asset.sampler);
+        let sampler_descriptor = match megaui_texture.params.sampler {
+            Some(sampler_descriptor) => sampler_descriptor,
+            None if texture_descriptor.mip_level_count > 1 => SamplerDescriptor {
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Linear,
+                ..texture_asset.sampler
+            },
+            None => texture_asset.sampler,
+        };
+        let sampler = render_resource_context.create_sampler(&sampler_descriptor);
 
         let texture_bind_group = BindGroup::build()
             .add_binding(0, RenderResourceBinding::Texture(texture))
@@ -567,57 +1932,160 @@ impl MegaUiNode {
         );
 
         let texture_resource = TextureResource {
+            handle: megaui_texture.handle.clone_weak(),
+            generation: megaui_texture.generation,
             descriptor: texture_descriptor,
             texture,
             sampler,
             bind_group: texture_bind_group,
+            size_bytes: texture_data.size_bytes(),
+            // Newly created textures aren't evicted in the same frame.
+            last_drawn_frame: self.frame,
         };
-        Self::copy_texture(render_context, &texture_resource, texture_asset);
+        let upload_bytes = Self::copy_texture(render_context, &texture_resource, &texture_data);
+        self.record_texture_upload(upload_bytes);
         log::debug!("Texture created: {:?}", texture_resource);
         self.texture_resources
-            .insert(texture_handle, texture_resource);
+            .insert(texture_key.clone(), texture_resource);
     }
 
-    fn remove_texture(
-        &mut self,
-        render_context: &mut dyn RenderContext,
-        texture_handle: &Handle<Texture>,
-    ) {
-        let texture_resource = match self.texture_resources.remove(texture_handle) {
+    fn remove_texture(&mut self, render_context: &mut dyn RenderContext, texture_key: &TextureKey) {
+        let texture_resource = match self.texture_resources.remove(texture_key) {
             Some(texture_resource) => texture_resource,
             None => return,
         };
-        log::debug!("Removing a texture: ${:?}", texture_handle);
+        log::debug!("Removing a texture: ${:?}", texture_resource.handle);
 
         let render_resource_context = render_context.resources();
         render_resource_context.remove_texture(texture_resource.texture);
         render_resource_context.remove_sampler(texture_resource.sampler);
     }
 
+    fn is_upload_budget_exhausted(&self) -> bool {
+        if self.stats.texture_uploads == 0 {
+            return false;
+        }
+        match self.texture_upload_budget {
+            Some(MegaUiTextureUploadBudget::Bytes(bytes)) => {
+                self.stats.texture_upload_bytes >= bytes
+            }
+            Some(MegaUiTextureUploadBudget::Count(count)) => self.stats.texture_uploads >= count,
+            None => false,
+        }
+    }
+
+    fn init_placeholder_texture(&mut self, render_context: &mut dyn RenderContext) {
+        if let Some(placeholder_texture) = &self.placeholder_texture {
+            render_context.resources().create_bind_group(
+                self.texture_bind_group_descriptor.as_ref().unwrap().id,
+                &placeholder_texture.bind_group,
+            );
+            return;
+        }
+
+        // An opaque texel looks the same with straight and premultiplied alpha.
+        let texture_data = TextureData {
+            descriptor: TextureDescriptor {
+                size: Extent3d::new(1, 1, 1),
+                format: TextureFormat::Rgba8Unorm,
+                usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+                ..Default::default()
+            },
+            data: vec![128, 128, 128, 255],
+            mip_levels: Vec::new(),
+        };
+
+        let render_resource_context = render_context.resources();
+        let texture = render_resource_context.create_texture(texture_data.descriptor);
+        let sampler = render_resource_context.create_sampler(&SamplerDescriptor::default());
+        let bind_group = BindGroup::build()
+            .add_binding(0, RenderResourceBinding::Texture(texture))
+            .add_binding(1, RenderResourceBinding::Sampler(sampler))
+            .finish();
+        render_resource_context.create_bind_group(
+            self.texture_bind_group_descriptor.as_ref().unwrap().id,
+            &bind_group,
+        );
+
+        let placeholder_texture = TextureResource {
+            handle: Handle::default(),
+            generation: 0,
+            descriptor: texture_data.descriptor,
+            texture,
+            sampler,
+            bind_group,
+            size_bytes: texture_data.size_bytes(),
+            last_drawn_frame: 0,
+        };
+        Self::copy_texture(render_context, &placeholder_texture, &texture_data);
+        self.placeholder_texture = Some(placeholder_texture);
+    }
+
+    // Returns the bind group a draw command is drawn with, if its texture can be displayed.
+    fn texture_bind_group(&self, draw_command: &DrawCommand) -> Option<&BindGroup> {
+        match self.texture_resources.get(&draw_command.texture_key) {
+            Some(texture_resource) => Some(&texture_resource.bind_group),
+            None if draw_command.is_texture_pending => self
+                .placeholder_texture
+                .as_ref()
+                .map(|placeholder_texture| &placeholder_texture.bind_group),
+            None => None,
+        }
+    }
+
+    fn record_texture_upload(&mut self, upload_bytes: usize) {
+        self.stats.texture_uploads += 1;
+        self.stats.texture_upload_bytes += upload_bytes;
+    }
+
+    // Returns the number of uploaded bytes.
     fn copy_texture(
         render_context: &mut dyn RenderContext,
         texture_resource: &TextureResource,
-        texture: &Texture,
-    ) {
-        let width = texture.size.width as usize;
-        let aligned_width = render_context.resources().get_aligned_texture_size(width);
-        let format_size = texture.format.pixel_size();
-
-        let mut aligned_data = vec![
-            0;
-            format_size
-                * aligned_width
-                * texture.size.height as usize
-                * texture.size.depth as usize
-        ];
-        texture
-            .data
-            .chunks_exact(format_size * width)
-            .enumerate()
-            .for_each(|(index, row)| {
-                let offset = index * aligned_width * format_size;
-                aligned_data[offset..(offset + width * format_size)].copy_from_slice(row);
-            });
+        texture_data: &TextureData,
+    ) -> usize {
+        let size = texture_data.descriptor.size;
+
+        let levels = std::iter::once(&texture_data.data).chain(&texture_data.mip_levels);
+        for (mip_level, data) in levels.enumerate() {
+            let (width, height) = mip_level_size(size.width, size.height, mip_level as u32);
+            Self::copy_texture_region(
+                render_context,
+                texture_resource.texture,
+                texture_data.descriptor.format,
+                mip_level as u32,
+                TextureRegion {
+                    x: 0,
+                    y: 0,
+                    width,
+                    height,
+                },
+                data,
+            );
+        }
+        texture_data.size_bytes()
+    }
+
+    // Copies tightly packed rows of a region to a mip level of a texture, returns the size
+    // of the data.
+    fn copy_texture_region(
+        render_context: &mut dyn RenderContext,
+        texture: TextureId,
+        format: TextureFormat,
+        mip_level: u32,
+        region: TextureRegion,
+        data: &[u8],
+    ) -> usize {
+        let format_size = format.pixel_size();
+        let aligned_width = render_context
+            .resources()
+            .get_aligned_texture_size(region.width as usize);
+
+        let aligned_data = align_rows(
+            data,
+            format_size * region.width as usize,
+            format_size * aligned_width,
+        );
         let texture_buffer = render_context.resources().create_buffer_with_data(
             BufferInfo {
                 buffer_usage: BufferUsage::COPY_SRC,
@@ -630,12 +2098,13 @@ impl MegaUiNode {
             texture_buffer,
             0,
             (format_size * aligned_width) as u32,
-            texture_resource.texture,
-            [0, 0, 0],
-            0,
-            texture_resource.descriptor.size,
+            texture,
+            [region.x, region.y, 0],
+            mip_level,
+            Extent3d::new(region.width, region.height, 1),
         );
         render_context.resources().remove_buffer(texture_buffer);
+        data.len()
     }
 
     fn update_buffers(
@@ -667,6 +2136,243 @@ impl MegaUiNode {
     }
 }
 
+// Returns keys of the textures referenced by draw lists of the visible contexts.
+fn drawn_texture_keys(layers: &WindowLayers) -> HashSet<TextureKey> {
+    layers
+        .iter()
+        .filter(|(_, megaui_context)| megaui_context.visible)
+        .flat_map(|(layer, megaui_context)| {
+            megaui_context
+                .ui_draw_lists
+                .iter()
+                .map(move |draw_list| TextureKey {
+                    layer: layer.clone(),
+                    texture_id: draw_list.texture,
+                })
+        })
+        .collect()
+}
+
+// Returns the context a texture key refers to.
+fn layer_context<'a>(
+    layers: &'a WindowLayers,
+    texture_key: &TextureKey,
+) -> Option<&'a MegaUiContext> {
+    layers
+        .iter()
+        .find(|(layer, _)| *layer == texture_key.layer)
+        .map(|(_, megaui_context)| &**megaui_context)
+}
+
+// Returns a megaui texture a texture key refers to, if it's still registered.
+fn layer_megaui_texture<'a>(
+    layers: &'a WindowLayers,
+    texture_key: &TextureKey,
+) -> Option<&'a MegaUiTexture> {
+    layer_context(layers, texture_key)?.megaui_texture(texture_key.texture_id)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn validate_shader(shader: &Shader) -> Result<(), ShaderError> {
+    shader.get_spirv(None).map(|_| ())
+}
+
+// WebGL2 compiles GLSL shaders itself, there's nothing we can validate beforehand.
+#[cfg(target_arch = "wasm32")]
+fn validate_shader(_shader: &Shader) -> Result<(), ShaderError> {
+    Ok(())
+}
+
+// Pushes a quad of a (rounded) rect expanded by the blur radius.
+fn push_decoration_quad(
+    vertex_buffer: &mut Vec<u8>,
+    index_buffer: &mut Vec<u32>,
+    rect: megaui::Rect,
+    blur: f32,
+    corner_radius: f32,
+    color: [f32; 4],
+) {
+    let (center, half_size, corner_radius) = rounded_rect(rect, corner_radius);
+    let shape = [half_size[0], half_size[1], corner_radius, blur];
+
+    let offset = (vertex_buffer.len() / DECORATION_VERTEX_SIZE) as u32;
+    let (left, top) = (rect.x - blur, rect.y - blur);
+    let (right, bottom) = (rect.x + rect.w + blur, rect.y + rect.h + blur);
+    for [x, y] in &[[left, top], [right, top], [right, bottom], [left, bottom]] {
+        vertex_buffer.extend_from_slice([*x, *y, 0.0].as_bytes());
+        vertex_buffer.extend_from_slice([x - center[0], y - center[1]].as_bytes());
+        vertex_buffer.extend_from_slice(color.as_bytes());
+        vertex_buffer.extend_from_slice(shape.as_bytes());
+    }
+    index_buffer.extend_from_slice(&[
+        offset,
+        offset + 1,
+        offset + 2,
+        offset,
+        offset + 2,
+        offset + 3,
+    ]);
+}
+
+// Returns the center and the half size of a rect, and the corner radius clamped to the latter.
+fn rounded_rect(rect: megaui::Rect, corner_radius: f32) -> ([f32; 2], [f32; 2], f32) {
+    let half_size = [rect.w / 2.0, rect.h / 2.0];
+    let center = [rect.x + half_size[0], rect.y + half_size[1]];
+    let corner_radius = corner_radius.max(0.0).min(half_size[0].min(half_size[1]));
+    (center, half_size, corner_radius)
+}
+
+// Sorts window rects by the order megaui draws the windows in (focused windows are drawn last),
+// which can differ from the order the rects were recorded in. A window is recognized by the first
+// draw list that has a vertex at the top-left corner of its rect (the window background), rects
+// that aren't found are considered to be drawn first.
+fn sort_window_rects(window_rects: &mut [megaui::Rect], draw_lists: &[megaui::DrawList]) {
+    window_rects.sort_by_cached_key(|rect| first_window_draw_list(*rect, draw_lists));
+}
+
+// Returns the window rect each draw list belongs to. A window owns the draw lists from its first
+// one up to the first draw list of the window drawn next, draw lists preceding all of the windows
+// don't belong to any. Rects have to be sorted with `sort_window_rects`.
+fn assign_draw_lists_to_windows(
+    window_rects: &[megaui::Rect],
+    draw_lists: &[megaui::DrawList],
+) -> Vec<Option<megaui::Rect>> {
+    let window_starts = window_rects
+        .iter()
+        .filter_map(|rect| first_window_draw_list(*rect, draw_lists).map(|start| (start, *rect)))
+        .collect::<Vec<_>>();
+    let mut windows = vec![None; draw_lists.len()];
+    for (i, (start, rect)) in window_starts.iter().enumerate() {
+        let end = window_starts
+            .get(i + 1)
+            .map_or(draw_lists.len(), |(next_start, _)| *next_start);
+        for window in &mut windows[*start..end] {
+            *window = Some(*rect);
+        }
+    }
+    windows
+}
+
+fn first_window_draw_list(
+    window_rect: megaui::Rect,
+    draw_lists: &[megaui::DrawList],
+) -> Option<usize> {
+    draw_lists.iter().position(|draw_list| {
+        draw_list.vertices.iter().any(|vertex| {
+            (vertex.pos[0] - window_rect.x).abs() < 0.01
+                && (vertex.pos[1] - window_rect.y).abs() < 0.01
+        })
+    })
+}
+
+// Position, local position, color and shape.
+const DECORATION_VERTEX_SIZE: usize = std::mem::size_of::<[f32; 3 + 2 + 4 + 4]>();
+
+fn vertex_buffer_descriptor(
+    name: &'static str,
+    attributes: &[(&'static str, VertexFormat)],
+) -> VertexBufferDescriptor {
+    let mut offset = 0;
+    let attributes = attributes
+        .iter()
+        .enumerate()
+        .map(|(shader_location, (name, format))| {
+            let attribute = VertexAttributeDescriptor {
+                name: Cow::from(*name),
+                offset,
+                format: *format,
+                shader_location: shader_location as u32,
+            };
+            offset += format.get_size();
+            attribute
+        })
+        .collect();
+    VertexBufferDescriptor {
+        name: Cow::from(name),
+        stride: offset,
+        step_mode: InputStepMode::Vertex,
+        attributes,
+    }
+}
+
+// Is shared with the rasterizer, which clips draw lists the same way.
+pub struct ScissorMapping {
+    pub viewport: MegaUiViewport,
+    pub transform: MegaUiAffineTransform,
+    // UI units to logical pixels.
+    pub ui_scale: f32,
+    // Logical pixels to render target pixels.
+    pub pixel_scale: (f32, f32),
+    pub target_size: (u32, u32),
+}
+
+impl ScissorMapping {
+    // Converts a clipping zone (in UI units) to a scissor rect. The rect is clamped to the viewport
+    // and to the render target, as rounding can make it slightly exceed the target.
+    pub fn scissor_rect(&self, clipping_zone: Option<megaui::Rect>) -> (u32, u32, u32, u32) {
+        let viewport = &self.viewport;
+        let (mut left, mut top, mut right, mut bottom) = (
+            viewport.x,
+            viewport.y,
+            viewport.x + viewport.width,
+            viewport.y + viewport.height,
+        );
+        if let Some(clipping_zone) = clipping_zone {
+            // Transformed zones are clipped by their bounding boxes.
+            let corners = [
+                [clipping_zone.x, clipping_zone.y],
+                [clipping_zone.x + clipping_zone.w, clipping_zone.y],
+                [clipping_zone.x, clipping_zone.y + clipping_zone.h],
+                [
+                    clipping_zone.x + clipping_zone.w,
+                    clipping_zone.y + clipping_zone.h,
+                ],
+            ];
+            let (mut min, mut max) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+            for corner in &corners {
+                let [x, y] = self.transform.transform_point(*corner);
+                min = [min[0].min(x), min[1].min(y)];
+                max = [max[0].max(x), max[1].max(y)];
+            }
+            left = left.max(viewport.x + min[0] * self.ui_scale);
+            top = top.max(viewport.y + min[1] * self.ui_scale);
+            right = right.min(viewport.x + max[0] * self.ui_scale);
+            bottom = bottom.min(viewport.y + max[1] * self.ui_scale);
+        }
+
+        let to_pixels =
+            |value: f32, scale: f32, size: u32| ((value * scale).max(0.0) as u32).min(size);
+        let left = to_pixels(left, self.pixel_scale.0, self.target_size.0);
+        let top = to_pixels(top, self.pixel_scale.1, self.target_size.1);
+        let right = to_pixels(right, self.pixel_scale.0, self.target_size.0).max(left);
+        let bottom = to_pixels(bottom, self.pixel_scale.1, self.target_size.1).max(top);
+        (left, top, right - left, bottom - top)
+    }
+}
+
+// Builds the bind group of the window uniforms that are declared by the descriptor, as custom
+// shaders may omit all but the transform uniform.
+fn window_uniforms_bind_group(
+    bind_group_descriptor: &BindGroupDescriptor,
+    render_resource_bindings: &RenderResourceBindings,
+    window_id: WindowId,
+) -> BindGroup {
+    let mut bind_group = BindGroup::build();
+    for binding in &bind_group_descriptor.bindings {
+        let name = WINDOW_UNIFORM_BINDING_NAMES
+            .iter()
+            .find(|name| **name == binding.name)
+            .copied()
+            .unwrap_or_else(|| panic!("Unexpected megaui uniform `{}`", binding.name));
+        let resource = render_resource_bindings
+            .get(&window_resource_binding_name(name, window_id))
+            .unwrap()
+            .clone();
+        bind_group = bind_group.add_binding(binding.index, resource);
+    }
+    bind_group.finish()
+}
+
 fn find_bind_group_by_binding_name(
     pipeline_layout: &PipelineLayout,
     binding_name: &str,
@@ -682,3 +2388,49 @@ fn find_bind_group_by_binding_name(
         })
         .cloned()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::quad;
+    use megaui::Rect;
+
+    #[test]
+    fn test_sort_window_rects() {
+        let (a, b) = (
+            Rect::new(0.0, 0.0, 10.0, 10.0),
+            Rect::new(5.0, 5.0, 10.0, 10.0),
+        );
+        let unknown = Rect::new(50.0, 50.0, 10.0, 10.0);
+        // The window `a` has been focused, so it's drawn last.
+        let mut window_rects = vec![a, b, unknown];
+        sort_window_rects(
+            &mut window_rects,
+            &[
+                quad(b.x, b.y, b.w, b.h, [1.0; 4]),
+                quad(a.x, a.y, a.w, a.h, [1.0; 4]),
+            ],
+        );
+        assert_eq!(window_rects, vec![unknown, b, a]);
+    }
+
+    #[test]
+    fn test_assign_draw_lists_to_windows() {
+        let (a, b) = (
+            Rect::new(0.0, 0.0, 10.0, 10.0),
+            Rect::new(5.0, 5.0, 10.0, 10.0),
+        );
+        let unknown = Rect::new(50.0, 50.0, 10.0, 10.0);
+        let draw_lists = [
+            quad(20.0, 20.0, 5.0, 5.0, [1.0; 4]),
+            quad(b.x, b.y, b.w, b.h, [1.0; 4]),
+            quad(6.0, 6.0, 5.0, 5.0, [1.0; 4]),
+            quad(a.x, a.y, a.w, a.h, [1.0; 4]),
+            quad(1.0, 1.0, 5.0, 5.0, [1.0; 4]),
+        ];
+        assert_eq!(
+            assign_draw_lists_to_windows(&[unknown, b, a], &draw_lists),
+            vec![None, Some(b), Some(b), Some(a), Some(a)]
+        );
+    }
+}
@@ -0,0 +1,650 @@
+//! A software rasterizer for megaui draw lists, which allows checking the UI in environments
+//! without a GPU (for instance, in pixel-level regression tests).
+//!
+//! The rasterizer mirrors the GPU path: positions are transformed with the same scale factor,
+//! viewport and UI transform, clipping zones become scissor rects, and textures are prepared
+//! the same way as for uploading. Opacity and the color filter are applied as the megaui shaders
+//! apply them. Blending happens in linear space, and the result is encoded as sRGB, as the default
+//! swap chain format is. Colors may differ by one step from the GPU output due to blending
+//! precision.
+//!
+//! Window decorations and the background blur aren't rasterized.
+
+use crate::{
+    megaui_node::ScissorMapping, texture::prepare_texture_data, MegaUiAffineTransform,
+    MegaUiBlendMode, MegaUiColorFilter, MegaUiContext, MegaUiPipelineConfig, MegaUiSettings,
+    MegaUiTexture, MegaUiTextureParams, MegaUiViewport,
+};
+use bevy::{
+    asset::Assets,
+    render::texture::{FilterMode, Texture, TextureFormat},
+    window::Window,
+};
+use megaui::{DrawList, Rect};
+use std::collections::HashMap;
+
+/// Params of [MegaUiContext::rasterize].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterizeParams {
+    /// Image width in physical pixels.
+    pub width: u32,
+    /// Image height in physical pixels.
+    pub height: u32,
+    /// The scale factor of the window, which converts logical pixels to physical ones
+    /// (`1.0` by default).
+    pub window_scale_factor: f32,
+    /// Should match [MegaUiSettings::scale_factor] (`1.0` by default).
+    pub scale_factor: f32,
+    /// Should match the entry of the window in [MegaUiSettings::viewports], `None` stands
+    /// for the whole image (default).
+    pub viewport: Option<MegaUiViewport>,
+    /// Should match the entry of the window in [MegaUiSettings::transforms]
+    /// ([MegaUiAffineTransform::IDENTITY] by default).
+    pub transform: MegaUiAffineTransform,
+    /// Should match [MegaUiSettings::opacity] (`1.0` by default).
+    pub opacity: f32,
+    /// Should match [MegaUiSettings::color_filter] (`None` by default).
+    pub color_filter: Option<MegaUiColorFilter>,
+    /// Should match [crate::MegaUiPipelineConfig::blend_mode]
+    /// ([MegaUiBlendMode::Straight] by default).
+    pub blend_mode: MegaUiBlendMode,
+    /// Linear RGBA color the image is cleared with (transparent black by default).
+    pub clear_color: [f32; 4],
+}
+
+impl Default for RasterizeParams {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            window_scale_factor: 1.0,
+            scale_factor: 1.0,
+            viewport: None,
+            transform: MegaUiAffineTransform::IDENTITY,
+            opacity: 1.0,
+            color_filter: None,
+            blend_mode: MegaUiBlendMode::Straight,
+            clear_color: [0.0; 4],
+        }
+    }
+}
+
+impl RasterizeParams {
+    /// Returns params that match the settings of a window, the image covers the whole window.
+    pub fn for_window(
+        window: &Window,
+        megaui_settings: &MegaUiSettings,
+        megaui_pipeline_config: &MegaUiPipelineConfig,
+    ) -> Self {
+        Self {
+            width: window.physical_width(),
+            height: window.physical_height(),
+            window_scale_factor: window.scale_factor() as f32,
+            scale_factor: megaui_settings.scale_factor as f32,
+            viewport: megaui_settings.viewports.get(&window.id()).copied(),
+            transform: megaui_settings.transform(window.id()),
+            opacity: megaui_settings.opacity,
+            color_filter: megaui_settings.color_filter,
+            blend_mode: megaui_pipeline_config.blend_mode,
+            ..Default::default()
+        }
+    }
+}
+
+/// An RGBA image with sRGB encoded colors.
+///
+/// The data can be saved as PNG with the `image` crate, e.g.
+/// `image::RgbaImage::from_raw(image.width, image.height, image.data)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RasterImage {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Tightly packed rows of RGBA pixels.
+    pub data: Vec<u8>,
+}
+
+impl RasterImage {
+    /// Returns a pixel at the passed coordinates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are out of the image bounds.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(x < self.width && y < self.height);
+        let offset = (y * self.width + x) as usize * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.data[offset..offset + 4]);
+        pixel
+    }
+}
+
+impl MegaUiContext {
+    /// Renders the current UI frame and rasterizes it on the CPU.
+    ///
+    /// The UI is rendered the same way the megaui pass renders it, so this is meant to be used
+    /// in apps that don't render with a GPU. As the megaui pass does, the caller must call
+    /// [megaui::Ui::new_frame] after rasterizing, before drawing the next frame. Textures that
+    /// are still loading or can't be displayed are skipped, as they are by the megaui pass.
+    pub fn rasterize(
+        &mut self,
+        texture_assets: &Assets<Texture>,
+        params: &RasterizeParams,
+    ) -> RasterImage {
+        self.render_draw_lists();
+        let draw_lists = &self.ui_draw_lists;
+
+        let mut textures = HashMap::new();
+        for draw_list in draw_lists {
+            if textures.contains_key(&draw_list.texture) {
+                continue;
+            }
+            let texture = self
+                .megaui_texture(draw_list.texture)
+                .and_then(|texture| RasterTexture::new(texture, texture_assets, params));
+            if let Some(texture) = texture {
+                textures.insert(draw_list.texture, texture);
+            }
+        }
+
+        rasterize_draw_lists(draw_lists, &textures, params)
+    }
+}
+
+// A texture with linear colors, alpha is premultiplied if the blend mode expects it.
+struct RasterTexture {
+    width: u32,
+    height: u32,
+    texels: Vec<[f32; 4]>,
+    filter: FilterMode,
+    uv_rect: Option<Rect>,
+}
+
+impl RasterTexture {
+    fn new(
+        megaui_texture: &MegaUiTexture,
+        texture_assets: &Assets<Texture>,
+        params: &RasterizeParams,
+    ) -> Option<Self> {
+        let texture_asset = texture_assets.get(&megaui_texture.handle)?;
+        Self::from_texture(texture_asset, &megaui_texture.params, params)
+    }
+
+    fn from_texture(
+        texture_asset: &Texture,
+        texture_params: &MegaUiTextureParams,
+        params: &RasterizeParams,
+    ) -> Option<Self> {
+        let texture_data = prepare_texture_data(
+            texture_asset,
+            texture_params,
+            params.blend_mode.premultiplied_alpha(),
+        )
+        .ok()?;
+
+        let format = texture_data.descriptor.format;
+        let texels = match format {
+            TextureFormat::Rgba16Float => texture_data
+                .data
+                .chunks_exact(8)
+                .map(|texel| {
+                    let channel =
+                        |i: usize| f16_to_f32(u16::from_le_bytes([texel[i * 2], texel[i * 2 + 1]]));
+                    [channel(0), channel(1), channel(2), channel(3)]
+                })
+                .collect(),
+            _ => {
+                let is_srgb = matches!(
+                    format,
+                    TextureFormat::Rgba8UnormSrgb | TextureFormat::Bgra8UnormSrgb
+                );
+                let is_bgra = matches!(
+                    format,
+                    TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+                );
+                texture_data
+                    .data
+                    .chunks_exact(4)
+                    .map(|texel| {
+                        let (r, b) = if is_bgra {
+                            (texel[2], texel[0])
+                        } else {
+                            (texel[0], texel[2])
+                        };
+                        let color = |c: u8| {
+                            let c = c as f32 / 255.0;
+                            if is_srgb {
+                                srgb_to_linear(c)
+                            } else {
+                                c
+                            }
+                        };
+                        [color(r), color(texel[1]), color(b), texel[3] as f32 / 255.0]
+                    })
+                    .collect()
+            }
+        };
+
+        let size = texture_data.descriptor.size;
+        let sampler = texture_params
+            .sampler
+            .as_ref()
+            .unwrap_or(&texture_asset.sampler);
+        Some(Self {
+            width: size.width,
+            height: size.height,
+            texels,
+            filter: sampler.mag_filter,
+            uv_rect: texture_params.region.map(|region| {
+                Rect::new(
+                    region.x / size.width as f32,
+                    region.y / size.height as f32,
+                    region.w / size.width as f32,
+                    region.h / size.height as f32,
+                )
+            }),
+        })
+    }
+
+    fn texel(&self, x: i64, y: i64) -> [f32; 4] {
+        // Only `ClampToEdge` addressing is supported.
+        let x = x.max(0).min(self.width as i64 - 1) as usize;
+        let y = y.max(0).min(self.height as i64 - 1) as usize;
+        self.texels[y * self.width as usize + x]
+    }
+
+    fn sample(&self, uv: [f32; 2]) -> [f32; 4] {
+        let uv = self.uv_rect.map_or(uv, |uv_rect| {
+            [uv_rect.x + uv[0] * uv_rect.w, uv_rect.y + uv[1] * uv_rect.h]
+        });
+        let x = uv[0] * self.width as f32;
+        let y = uv[1] * self.height as f32;
+        match self.filter {
+            FilterMode::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            FilterMode::Linear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = lerp(self.texel(x0, y0), self.texel(x0 + 1, y0), fx);
+                let bottom = lerp(self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1), fx);
+                lerp(top, bottom, fy)
+            }
+        }
+    }
+}
+
+fn rasterize_draw_lists(
+    draw_lists: &[DrawList],
+    textures: &HashMap<Option<u32>, RasterTexture>,
+    params: &RasterizeParams,
+) -> RasterImage {
+    let (width, height) = (params.width as usize, params.height as usize);
+    let mut pixels = vec![params.clear_color; width * height];
+
+    let window_scale_factor = params.window_scale_factor;
+    let viewport = params.viewport.unwrap_or(MegaUiViewport {
+        x: 0.0,
+        y: 0.0,
+        width: params.width as f32 / window_scale_factor,
+        height: params.height as f32 / window_scale_factor,
+    });
+    let scissor_mapping = ScissorMapping {
+        viewport,
+        transform: params.transform,
+        ui_scale: params.scale_factor,
+        pixel_scale: (window_scale_factor, window_scale_factor),
+        target_size: (params.width, params.height),
+    };
+    // Mirrors `megaui.vert`: positions are transformed in UI units, then mapped to the viewport.
+    let project = |position: [f32; 3]| {
+        let [x, y] = params.transform.transform_point([position[0], position[1]]);
+        [
+            (viewport.x + x * params.scale_factor) * window_scale_factor,
+            (viewport.y + y * params.scale_factor) * window_scale_factor,
+        ]
+    };
+    let premultiplied_alpha = params.blend_mode.premultiplied_alpha();
+    let opacity = params.opacity.max(0.0).min(1.0);
+    let color_scale = if premultiplied_alpha {
+        [opacity; 4]
+    } else {
+        [1.0, 1.0, 1.0, opacity]
+    };
+    let color_matrix = params.color_filter.map_or(
+        [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+        ],
+        |color_filter| color_filter.matrix(),
+    );
+
+    for draw_list in draw_lists {
+        let texture = match textures.get(&draw_list.texture) {
+            Some(texture) => texture,
+            None => continue,
+        };
+
+        // Scissor rects are truncated to whole pixels, the same way the megaui pass does it.
+        let (x, y, w, h) = scissor_mapping.scissor_rect(draw_list.clipping_zone);
+        let (clip_min, clip_max) = (
+            (x as usize, y as usize),
+            ((x + w) as usize, (y + h) as usize),
+        );
+
+        for triangle in draw_list.indices.chunks_exact(3) {
+            let vertices = [
+                &draw_list.vertices[triangle[0] as usize],
+                &draw_list.vertices[triangle[1] as usize],
+                &draw_list.vertices[triangle[2] as usize],
+            ];
+            let positions = [
+                project(vertices[0].pos),
+                project(vertices[1].pos),
+                project(vertices[2].pos),
+            ];
+            let area = edge(positions[0], positions[1], positions[2]);
+            if area == 0.0 {
+                continue;
+            }
+
+            let min_x = positions.iter().map(|p| p[0]).fold(f32::MAX, f32::min);
+            let max_x = positions.iter().map(|p| p[0]).fold(f32::MIN, f32::max);
+            let min_y = positions.iter().map(|p| p[1]).fold(f32::MAX, f32::min);
+            let max_y = positions.iter().map(|p| p[1]).fold(f32::MIN, f32::max);
+            let x_range = (min_x.floor().max(clip_min.0 as f32) as usize)
+                ..(max_x.ceil().max(0.0) as usize).min(clip_max.0);
+            let y_range = (min_y.floor().max(clip_min.1 as f32) as usize)
+                ..(max_y.ceil().max(0.0) as usize).min(clip_max.1);
+
+            for y in y_range {
+                for x in x_range.clone() {
+                    let point = [x as f32 + 0.5, y as f32 + 0.5];
+                    let mut weights = [0.0; 3];
+                    let mut is_inside = true;
+                    for (i, weight) in weights.iter_mut().enumerate() {
+                        let (a, b) = (positions[(i + 1) % 3], positions[(i + 2) % 3]);
+                        *weight = edge(a, b, point) / area;
+                        if *weight < 0.0 || *weight == 0.0 && !owns_edge(a, b, area) {
+                            is_inside = false;
+                            break;
+                        }
+                    }
+                    if !is_inside {
+                        continue;
+                    }
+
+                    let interpolate = |attribute: &dyn Fn(usize) -> f32| {
+                        (0..3).map(|i| attribute(i) * weights[i]).sum::<f32>()
+                    };
+                    let uv = [
+                        interpolate(&|i| vertices[i].uv[0]),
+                        interpolate(&|i| vertices[i].uv[1]),
+                    ];
+                    let mut color = [0.0; 4];
+                    for (channel, value) in color.iter_mut().enumerate() {
+                        *value = interpolate(&|i| vertices[i].color[channel]);
+                    }
+                    if premultiplied_alpha {
+                        color = [
+                            color[0] * color[3],
+                            color[1] * color[3],
+                            color[2] * color[3],
+                            color[3],
+                        ];
+                    }
+
+                    let texel = texture.sample(uv);
+                    let source = filter_color(
+                        [
+                            color[0] * color_scale[0] * texel[0],
+                            color[1] * color_scale[1] * texel[1],
+                            color[2] * color_scale[2] * texel[2],
+                            color[3] * color_scale[3] * texel[3],
+                        ],
+                        &color_matrix,
+                        premultiplied_alpha,
+                    );
+                    let pixel = &mut pixels[y * width + x];
+                    *pixel = blend(params.blend_mode, source, *pixel);
+                }
+            }
+        }
+    }
+
+    let mut data = Vec::with_capacity(width * height * 4);
+    for pixel in pixels {
+        for &channel in &pixel[..3] {
+            data.push(to_u8(linear_to_srgb(channel)));
+        }
+        data.push(to_u8(pixel[3]));
+    }
+    RasterImage {
+        width: params.width,
+        height: params.height,
+        data,
+    }
+}
+
+// Mirrors the blend descriptors of `MegaUiBlendMode`.
+fn blend(blend_mode: MegaUiBlendMode, source: [f32; 4], destination: [f32; 4]) -> [f32; 4] {
+    let alpha = source[3];
+    let over_alpha = alpha + destination[3] * (1.0 - alpha);
+    let mut result = [0.0; 4];
+    for channel in 0..3 {
+        result[channel] = match blend_mode {
+            MegaUiBlendMode::Straight => {
+                source[channel] * alpha + destination[channel] * (1.0 - alpha)
+            }
+            MegaUiBlendMode::Premultiplied => {
+                source[channel] + destination[channel] * (1.0 - alpha)
+            }
+            MegaUiBlendMode::Additive => source[channel] * alpha + destination[channel],
+        };
+    }
+    result[3] = match blend_mode {
+        MegaUiBlendMode::Straight | MegaUiBlendMode::Premultiplied => over_alpha,
+        MegaUiBlendMode::Additive => destination[3],
+    };
+    result
+}
+
+// Mirrors `filterColor` of `megaui.frag`, offsets are scaled by alpha if colors are premultiplied.
+fn filter_color(
+    color: [f32; 4],
+    color_matrix: &[[f32; 4]; 3],
+    premultiplied_alpha: bool,
+) -> [f32; 4] {
+    let alpha_scale = if premultiplied_alpha { color[3] } else { 1.0 };
+    let mut filtered = color;
+    for (value, row) in filtered.iter_mut().zip(color_matrix) {
+        let channel =
+            row[0] * color[0] + row[1] * color[1] + row[2] * color[2] + row[3] * alpha_scale;
+        *value = channel.max(0.0).min(alpha_scale);
+    }
+    filtered
+}
+
+fn edge(a: [f32; 2], b: [f32; 2], point: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (point[1] - a[1]) - (b[1] - a[1]) * (point[0] - a[0])
+}
+
+// Decides which of two triangles sharing an edge owns the pixels lying exactly on it,
+// so that such pixels aren't blended twice. Triangles with the same winding traverse
+// the shared edge in opposite directions, so only one of them passes the check.
+fn owns_edge(a: [f32; 2], b: [f32; 2], area: f32) -> bool {
+    let (dx, dy) = if area > 0.0 {
+        (b[0] - a[0], b[1] - a[1])
+    } else {
+        (a[0] - b[0], a[1] - b[1])
+    };
+    dy > 0.0 || dy == 0.0 && dx < 0.0
+}
+
+fn lerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        a[3] + (b[3] - a[3]) * t,
+    ]
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{quad, texture};
+
+    fn rasterize(draw_lists: &[DrawList], params: &RasterizeParams) -> RasterImage {
+        let white_texture = texture(1, 1, 1, TextureFormat::Rgba8Unorm, vec![255; 4]);
+        let mut textures = HashMap::new();
+        textures.insert(
+            None,
+            RasterTexture::from_texture(&white_texture, &MegaUiTextureParams::default(), params)
+                .unwrap(),
+        );
+        rasterize_draw_lists(draw_lists, &textures, params)
+    }
+
+    #[test]
+    fn test_quad() {
+        let params = RasterizeParams {
+            width: 4,
+            height: 4,
+            ..Default::default()
+        };
+        let image = rasterize(&[quad(1.0, 1.0, 2.0, 2.0, [1.0, 0.0, 0.0, 1.0])], &params);
+
+        assert_eq!(image.data.len(), 4 * 4 * 4);
+        assert_eq!(image.pixel(0, 0), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(1, 1), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(2, 2), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(3, 3), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_shared_edges_are_blended_once() {
+        let params = RasterizeParams {
+            width: 4,
+            height: 4,
+            ..Default::default()
+        };
+        // The diagonal of the quad passes through pixel centers.
+        let image = rasterize(&[quad(0.5, 0.5, 3.0, 3.0, [1.0, 1.0, 1.0, 0.5])], &params);
+        for i in 1..3 {
+            assert_eq!(image.pixel(i, i), [188, 188, 188, 128]);
+        }
+    }
+
+    #[test]
+    fn test_clipping_and_scale_factor() {
+        let params = RasterizeParams {
+            width: 4,
+            height: 4,
+            scale_factor: 2.0,
+            ..Default::default()
+        };
+        let mut draw_list = quad(0.0, 0.0, 2.0, 2.0, [1.0; 4]);
+        draw_list.clipping_zone = Some(Rect::new(0.0, 0.0, 1.0, 2.0));
+        let image = rasterize(&[draw_list], &params);
+
+        assert_eq!(image.pixel(1, 3), [255, 255, 255, 255]);
+        assert_eq!(image.pixel(2, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_viewport_and_transform() {
+        let params = RasterizeParams {
+            width: 4,
+            height: 4,
+            window_scale_factor: 2.0,
+            viewport: Some(MegaUiViewport {
+                x: 1.0,
+                y: 0.0,
+                width: 1.0,
+                height: 2.0,
+            }),
+            transform: MegaUiAffineTransform::from_translation([0.5, 0.0]),
+            ..Default::default()
+        };
+        // The quad starts at the 3rd physical pixel, and it's clipped by the viewport.
+        let image = rasterize(&[quad(0.0, 0.0, 2.0, 2.0, [1.0; 4])], &params);
+
+        assert_eq!(image.pixel(3, 0), [255, 255, 255, 255]);
+        assert_eq!(image.pixel(3, 3), [255, 255, 255, 255]);
+        assert_eq!(image.pixel(2, 0), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(1, 1), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_opacity_and_color_filter() {
+        let params = RasterizeParams {
+            width: 1,
+            height: 1,
+            opacity: 0.5,
+            color_filter: Some(MegaUiColorFilter::Matrix([
+                [0.0, 0.0, 0.0, 1.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+            ])),
+            ..Default::default()
+        };
+        let image = rasterize(&[quad(0.0, 0.0, 1.0, 1.0, [0.0, 0.0, 0.0, 1.0])], &params);
+        assert_eq!(image.pixel(0, 0), [188, 0, 0, 128]);
+    }
+
+    #[test]
+    fn test_premultiplied_blending() {
+        let params = RasterizeParams {
+            width: 1,
+            height: 1,
+            blend_mode: MegaUiBlendMode::Premultiplied,
+            clear_color: [0.0, 0.0, 1.0, 1.0],
+            ..Default::default()
+        };
+        let image = rasterize(&[quad(0.0, 0.0, 1.0, 1.0, [1.0, 0.0, 0.0, 0.5])], &params);
+        assert_eq!(image.pixel(0, 0), [188, 0, 188, 255]);
+    }
+
+    #[test]
+    fn test_f16_to_f32() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x3800), 0.5);
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+    }
+}
@@ -0,0 +1,345 @@
+//! Draw list snapshots for regression tests.
+//!
+//! A snapshot captures vertices, indices, texture ids and clipping zones of a frame's draw lists.
+//! Floats are rounded, so snapshots are stable across platforms, and they are serialized to RON
+//! with a vertex per line, which makes them easy to diff.
+//!
+//! ```no_run
+//! # use bevy_megaui::{snapshot::DrawListsSnapshot, MegaUiContext};
+//! fn check_layout(context: &MegaUiContext) {
+//!     let snapshot = context.snapshot_draw_lists();
+//!     let expected =
+//!         DrawListsSnapshot::from_ron(&std::fs::read_to_string("tests/layout.ron").unwrap())
+//!             .unwrap();
+//!     snapshot.assert_matches(&expected);
+//! }
+//! ```
+
+use crate::MegaUiContext;
+use megaui::DrawList;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Floats are rounded to 4 decimal places.
+const PRECISION: f32 = 10_000.0;
+
+impl MegaUiContext {
+    /// Captures the draw lists of the last rendered UI frame.
+    ///
+    /// The draw lists are rendered by the megaui pass (or by `MegaUiContext::rasterize`) and kept
+    /// until the next frame is rendered, so a snapshot taken while drawing the UI captures
+    /// the previous frame. Taking a snapshot doesn't affect rendering.
+    pub fn snapshot_draw_lists(&self) -> DrawListsSnapshot {
+        DrawListsSnapshot::new(&self.ui_draw_lists)
+    }
+}
+
+/// Draw lists of a frame, see the [module docs](self).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DrawListsSnapshot {
+    /// Draw lists in the order they are drawn.
+    pub draw_lists: Vec<DrawListSnapshot>,
+}
+
+/// A snapshot of a single draw list.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DrawListSnapshot {
+    /// A megaui texture id, `None` stands for the font texture.
+    pub texture: Option<u32>,
+    /// A clipping zone as `(x, y, w, h)`.
+    pub clipping_zone: Option<(f32, f32, f32, f32)>,
+    /// Vertices.
+    pub vertices: Vec<VertexSnapshot>,
+    /// Indices grouped by triangles.
+    pub triangles: Vec<[u16; 3]>,
+}
+
+/// A snapshot of a vertex.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VertexSnapshot {
+    /// Position.
+    pub pos: [f32; 3],
+    /// Texture coordinates.
+    pub uv: [f32; 2],
+    /// RGBA color.
+    pub color: [f32; 4],
+}
+
+impl DrawListsSnapshot {
+    /// Captures draw lists, rounding floats.
+    pub fn new(draw_lists: &[DrawList]) -> Self {
+        Self {
+            draw_lists: draw_lists
+                .iter()
+                .map(|draw_list| DrawListSnapshot {
+                    texture: draw_list.texture,
+                    clipping_zone: draw_list.clipping_zone.map(|clipping_zone| {
+                        (
+                            round(clipping_zone.x),
+                            round(clipping_zone.y),
+                            round(clipping_zone.w),
+                            round(clipping_zone.h),
+                        )
+                    }),
+                    vertices: draw_list
+                        .vertices
+                        .iter()
+                        .map(|vertex| VertexSnapshot {
+                            pos: [
+                                round(vertex.pos[0]),
+                                round(vertex.pos[1]),
+                                round(vertex.pos[2]),
+                            ],
+                            uv: [round(vertex.uv[0]), round(vertex.uv[1])],
+                            color: [
+                                round(vertex.color[0]),
+                                round(vertex.color[1]),
+                                round(vertex.color[2]),
+                                round(vertex.color[3]),
+                            ],
+                        })
+                        .collect(),
+                    triangles: draw_list
+                        .indices
+                        .chunks_exact(3)
+                        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Serializes the snapshot to RON.
+    pub fn to_ron(&self) -> String {
+        // With this depth limit each vertex and triangle is written on a separate line.
+        let config = ron::ser::PrettyConfig::new().with_depth_limit(4);
+        ron::ser::to_string_pretty(self, config).expect("Failed to serialize a snapshot")
+    }
+
+    /// Deserializes a snapshot from RON.
+    pub fn from_ron(s: &str) -> Result<Self, ron::Error> {
+        ron::de::from_str(s)
+    }
+
+    /// Compares the snapshot with the expected one, returning the first difference.
+    pub fn compare(&self, expected: &DrawListsSnapshot) -> Result<(), SnapshotMismatch> {
+        for (index, (actual, expected)) in
+            self.draw_lists.iter().zip(&expected.draw_lists).enumerate()
+        {
+            if let Some(difference) = DrawListDifference::find(actual, expected) {
+                return Err(SnapshotMismatch::DrawList {
+                    index,
+                    difference,
+                    actual: Box::new(actual.clone()),
+                    expected: Box::new(expected.clone()),
+                });
+            }
+        }
+        if self.draw_lists.len() != expected.draw_lists.len() {
+            return Err(SnapshotMismatch::DrawListCount {
+                actual: self.draw_lists.len(),
+                expected: expected.draw_lists.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Works the same way as [DrawListsSnapshot::compare], but panics with a description
+    /// of the first difference.
+    pub fn assert_matches(&self, expected: &DrawListsSnapshot) {
+        if let Err(mismatch) = self.compare(expected) {
+            panic!("Draw lists don't match the snapshot: {}", mismatch);
+        }
+    }
+}
+
+/// The first difference between two snapshots, see [DrawListsSnapshot::compare].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotMismatch {
+    /// All the common draw lists match, but the number of draw lists is different.
+    DrawListCount {
+        /// Number of the actual draw lists.
+        actual: usize,
+        /// Number of the expected draw lists.
+        expected: usize,
+    },
+    /// A draw list differs.
+    DrawList {
+        /// Index of the draw list.
+        index: usize,
+        /// The first difference within the draw list.
+        difference: DrawListDifference,
+        /// The actual draw list.
+        actual: Box<DrawListSnapshot>,
+        /// The expected draw list.
+        expected: Box<DrawListSnapshot>,
+    },
+}
+
+impl fmt::Display for SnapshotMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotMismatch::DrawListCount { actual, expected } => {
+                write!(f, "expected {} draw lists, got {}", expected, actual)
+            }
+            SnapshotMismatch::DrawList {
+                index,
+                difference,
+                actual,
+                expected,
+            } => write!(
+                f,
+                "draw list #{} differs: {}\nactual: {:?}\nexpected: {:?}",
+                index, difference, actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotMismatch {}
+
+/// The first difference between two draw lists.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawListDifference {
+    /// Texture ids are different.
+    Texture,
+    /// Clipping zones are different.
+    ClippingZone,
+    /// Numbers of vertices are different.
+    VertexCount,
+    /// A vertex at the index is different.
+    Vertex(usize),
+    /// Numbers of triangles are different.
+    TriangleCount,
+    /// A triangle at the index is different.
+    Triangle(usize),
+}
+
+impl DrawListDifference {
+    fn find(actual: &DrawListSnapshot, expected: &DrawListSnapshot) -> Option<Self> {
+        if actual.texture != expected.texture {
+            return Some(DrawListDifference::Texture);
+        }
+        if actual.clipping_zone != expected.clipping_zone {
+            return Some(DrawListDifference::ClippingZone);
+        }
+        if let Some(i) = first_difference(&actual.vertices, &expected.vertices) {
+            return Some(DrawListDifference::Vertex(i));
+        }
+        if actual.vertices.len() != expected.vertices.len() {
+            return Some(DrawListDifference::VertexCount);
+        }
+        if let Some(i) = first_difference(&actual.triangles, &expected.triangles) {
+            return Some(DrawListDifference::Triangle(i));
+        }
+        if actual.triangles.len() != expected.triangles.len() {
+            return Some(DrawListDifference::TriangleCount);
+        }
+        None
+    }
+}
+
+impl fmt::Display for DrawListDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawListDifference::Texture => write!(f, "texture ids are different"),
+            DrawListDifference::ClippingZone => write!(f, "clipping zones are different"),
+            DrawListDifference::VertexCount => write!(f, "vertex counts are different"),
+            DrawListDifference::Vertex(i) => write!(f, "vertex #{} is different", i),
+            DrawListDifference::TriangleCount => write!(f, "triangle counts are different"),
+            DrawListDifference::Triangle(i) => write!(f, "triangle #{} is different", i),
+        }
+    }
+}
+
+fn first_difference<T: PartialEq>(actual: &[T], expected: &[T]) -> Option<usize> {
+    actual
+        .iter()
+        .zip(expected)
+        .position(|(actual, expected)| actual != expected)
+}
+
+fn round(value: f32) -> f32 {
+    let rounded = (value * PRECISION).round() / PRECISION;
+    // Avoids `-0.0` appearing in snapshots.
+    rounded + 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::quad;
+
+    // The second vertex of the quad depends on the width.
+    fn snapshot(w: f32) -> DrawListsSnapshot {
+        DrawListsSnapshot::new(&[quad(0.0, 0.0, w, 10.0, [1.0; 4])])
+    }
+
+    #[test]
+    fn test_round() {
+        assert_eq!(round(1.234_56), 1.2346);
+        assert_eq!(round(-0.000_01).to_string(), "0");
+        assert_eq!(round(2.0), 2.0);
+    }
+
+    #[test]
+    fn test_ron_round_trip() {
+        let snapshot = snapshot(1.5);
+        let ron = snapshot.to_ron();
+        assert_eq!(DrawListsSnapshot::from_ron(&ron).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_ui_frame() {
+        let mut ui = megaui::Ui::new();
+        megaui::widgets::Window::new(
+            megaui::hash!(),
+            megaui::Vector2::new(10.0, 10.0),
+            megaui::Vector2::new(100.0, 50.0),
+        )
+        .label("Window")
+        .ui(&mut ui, |ui| ui.label(None, "Label"));
+        let mut draw_lists = Vec::new();
+        ui.render(&mut draw_lists);
+
+        let snapshot = DrawListsSnapshot::new(&draw_lists);
+        assert!(!snapshot.draw_lists.is_empty());
+        assert_eq!(snapshot.draw_lists.len(), draw_lists.len());
+        for (draw_list_snapshot, draw_list) in snapshot.draw_lists.iter().zip(&draw_lists) {
+            assert_eq!(draw_list_snapshot.texture, draw_list.texture);
+            assert_eq!(draw_list_snapshot.vertices.len(), draw_list.vertices.len());
+            assert_eq!(
+                draw_list_snapshot.triangles.len() * 3,
+                draw_list.indices.len()
+            );
+        }
+        let ron = snapshot.to_ron();
+        assert_eq!(DrawListsSnapshot::from_ron(&ron).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(snapshot(1.5).compare(&snapshot(1.5)), Ok(()));
+
+        let mismatch = snapshot(2.0).compare(&snapshot(1.5)).unwrap_err();
+        match mismatch {
+            SnapshotMismatch::DrawList {
+                index, difference, ..
+            } => {
+                assert_eq!(index, 0);
+                assert_eq!(difference, DrawListDifference::Vertex(1));
+            }
+            _ => panic!("Unexpected mismatch: {:?}", mismatch),
+        }
+
+        let mut expected = snapshot(1.5);
+        expected.draw_lists.push(expected.draw_lists[0].clone());
+        assert_eq!(
+            snapshot(1.5).compare(&expected),
+            Err(SnapshotMismatch::DrawListCount {
+                actual: 1,
+                expected: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Fixtures shared by the unit tests.
+
+use crate::draw_megaui_window;
+use bevy::render::texture::{Extent3d, Texture, TextureDimension, TextureFormat};
+use megaui::{DrawList, Vector2, Vertex};
+
+pub fn vertex(x: f32, y: f32, color: [f32; 4]) -> Vertex {
+    Vertex {
+        pos: [x, y, 0.0],
+        uv: [0.0, 0.0],
+        color,
+    }
+}
+
+// A draw list of a single quad drawn with the font texture.
+pub fn quad(x: f32, y: f32, w: f32, h: f32, color: [f32; 4]) -> DrawList {
+    DrawList {
+        vertices: vec![
+            vertex(x, y, color),
+            vertex(x + w, y, color),
+            vertex(x + w, y + h, color),
+            vertex(x, y + h, color),
+        ],
+        indices: vec![0, 1, 2, 0, 2, 3],
+        texture: None,
+        clipping_zone: None,
+    }
+}
+
+pub fn texture(
+    width: u32,
+    height: u32,
+    layers: u32,
+    format: TextureFormat,
+    data: Vec<u8>,
+) -> Texture {
+    Texture::new(
+        Extent3d::new(width, height, layers),
+        TextureDimension::D2,
+        data,
+        format,
+    )
+}
+
+// Draws a frame with a single window the same way the app and the megaui pass do.
+pub fn draw_frame(ui: &mut megaui::Ui) -> Option<megaui::Rect> {
+    let window_rect = draw_megaui_window(
+        ui,
+        megaui::hash!(),
+        Vector2::new(10.0, 10.0),
+        Vector2::new(100.0, 50.0),
+        None,
+        |_| {},
+    );
+    ui.render(&mut Vec::new());
+    ui.new_frame(1.0 / 60.0);
+    window_rect
+}
@@ -0,0 +1,749 @@
+use crate::{MegaUiTextureParams, MegaUiTextureSwizzle};
+use bevy::render::texture::{
+    Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
+};
+
+/// Texture data that is ready to be copied to a GPU texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureData {
+    pub descriptor: TextureDescriptor,
+    /// Tightly packed rows of the texture.
+    pub data: Vec<u8>,
+    /// Tightly packed rows of the mip levels following the base one (if mipmaps are enabled).
+    pub mip_levels: Vec<Vec<u8>>,
+}
+
+impl TextureData {
+    /// Size of the data in bytes (including mip levels).
+    pub fn size_bytes(&self) -> usize {
+        self.data.len() + self.mip_levels.iter().map(Vec::len).sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureDataError {
+    UnsupportedFormat(TextureFormat),
+    LayerOutOfBounds { layer: u32, layers: u32 },
+    NotEnoughData { expected: usize, actual: usize },
+}
+
+/// A region of a texture in texels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TextureRegion {
+    /// Converts a rect in pixels to the texels it touches, clamped to the texture size.
+    /// Returns `None` if the region is empty.
+    pub fn from_rect(rect: megaui::Rect, width: u32, height: u32) -> Option<Self> {
+        let clamp = |value: f32, max: u32| (value.max(0.0) as u32).min(max);
+        let (min_x, min_y) = (clamp(rect.x.floor(), width), clamp(rect.y.floor(), height));
+        let (max_x, max_y) = (
+            clamp((rect.x + rect.w).ceil(), width),
+            clamp((rect.y + rect.h).ceil(), height),
+        );
+        if min_x >= max_x || min_y >= max_y {
+            return None;
+        }
+        Some(Self {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x,
+            height: max_y - min_y,
+        })
+    }
+}
+
+/// Picks the layer of a texture that is set in params and converts it to a format that
+/// the megaui shaders can display as is. Alpha is converted to premultiplied or straight one,
+/// depending on `premultiplied_alpha`.
+pub fn prepare_texture_data(
+    texture: &Texture,
+    params: &MegaUiTextureParams,
+    premultiplied_alpha: bool,
+) -> Result<TextureData, TextureDataError> {
+    let layer_data = layer_data(texture, params)?;
+    let (format, data) = convert_texels(texture.format, layer_data, params, premultiplied_alpha)?;
+    let mip_levels = if params.mipmaps {
+        generate_mip_levels(
+            format,
+            texture.size.width,
+            texture.size.height,
+            &data,
+            premultiplied_alpha,
+        )
+    } else {
+        Vec::new()
+    };
+    Ok(TextureData {
+        descriptor: texture_descriptor(texture, format, 1 + mip_levels.len() as u32),
+        data,
+        mip_levels,
+    })
+}
+
+/// Works the same way as [prepare_texture_data], but converts only a region of the base level.
+/// The descriptor still describes the whole texture, so it can be compared with the one
+/// of the existing GPU texture.
+///
+/// # Panics
+///
+/// Panics if the region is out of the texture bounds.
+pub fn prepare_texture_region_data(
+    texture: &Texture,
+    params: &MegaUiTextureParams,
+    premultiplied_alpha: bool,
+    region: TextureRegion,
+) -> Result<TextureData, TextureDataError> {
+    assert!(
+        region.x + region.width <= texture.size.width
+            && region.y + region.height <= texture.size.height,
+        "Texture region {:?} is out of bounds",
+        region
+    );
+    let layer_data = layer_data(texture, params)?;
+
+    let pixel_size = texture.format.pixel_size();
+    let row_size = texture.size.width as usize * pixel_size;
+    let region_row_size = region.width as usize * pixel_size;
+    let mut region_data = Vec::with_capacity(region_row_size * region.height as usize);
+    for y in region.y..region.y + region.height {
+        let offset = y as usize * row_size + region.x as usize * pixel_size;
+        region_data.extend_from_slice(&layer_data[offset..offset + region_row_size]);
+    }
+
+    let (format, data) = convert_texels(texture.format, &region_data, params, premultiplied_alpha)?;
+    let mip_level_count = if params.mipmaps && is_8bit_rgba(format) {
+        mip_level_count(texture.size.width, texture.size.height)
+    } else {
+        1
+    };
+    Ok(TextureData {
+        descriptor: texture_descriptor(texture, format, mip_level_count),
+        data,
+        mip_levels: Vec::new(),
+    })
+}
+
+fn layer_data<'a>(
+    texture: &'a Texture,
+    params: &MegaUiTextureParams,
+) -> Result<&'a [u8], TextureDataError> {
+    let layers = texture.size.depth;
+    if params.layer >= layers {
+        return Err(TextureDataError::LayerOutOfBounds {
+            layer: params.layer,
+            layers,
+        });
+    }
+
+    let layer_size =
+        texture.size.width as usize * texture.size.height as usize * texture.format.pixel_size();
+    let layer_offset = layer_size * params.layer as usize;
+    texture
+        .data
+        .get(layer_offset..layer_offset + layer_size)
+        .ok_or(TextureDataError::NotEnoughData {
+            expected: layer_size * layers as usize,
+            actual: texture.data.len(),
+        })
+}
+
+fn convert_texels(
+    format: TextureFormat,
+    data: &[u8],
+    params: &MegaUiTextureParams,
+    premultiplied_alpha: bool,
+) -> Result<(TextureFormat, Vec<u8>), TextureDataError> {
+    let (format, mut data) = convert_format(format, data, params.swizzle)?;
+    if params.premultiplied != premultiplied_alpha && is_8bit_rgba(format) {
+        convert_alpha(&mut data, premultiplied_alpha, is_srgb(format));
+    }
+    Ok((format, data))
+}
+
+fn texture_descriptor(
+    texture: &Texture,
+    format: TextureFormat,
+    mip_level_count: u32,
+) -> TextureDescriptor {
+    TextureDescriptor {
+        size: Extent3d::new(texture.size.width, texture.size.height, 1),
+        mip_level_count,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+    }
+}
+
+// Returns the number of mip levels (including the base one) `generate_mip_levels` produces.
+fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Returns the size of a mip level.
+pub fn mip_level_size(width: u32, height: u32, level: u32) -> (u32, u32) {
+    ((width >> level).max(1), (height >> level).max(1))
+}
+
+fn is_8bit_rgba(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::Rgba8Unorm
+            | TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Bgra8Unorm
+            | TextureFormat::Bgra8UnormSrgb
+    )
+}
+
+// Premultiplies or unpremultiplies colors of 8-bit RGBA (or BGRA) texels. sRGB colors are
+// converted in linear space, as they are decoded by samplers before being blended.
+fn convert_alpha(data: &mut [u8], premultiply: bool, srgb: bool) {
+    for texel in data.chunks_exact_mut(4) {
+        if srgb {
+            let alpha = texel[3] as f32 / 255.0;
+            for channel in &mut texel[..3] {
+                let color = srgb_to_linear(*channel as f32 / 255.0);
+                let color = if premultiply {
+                    color * alpha
+                } else if alpha > 0.0 {
+                    (color / alpha).min(1.0)
+                } else {
+                    0.0
+                };
+                *channel = (linear_to_srgb(color) * 255.0 + 0.5) as u8;
+            }
+            continue;
+        }
+
+        let alpha = texel[3] as u32;
+        for channel in &mut texel[..3] {
+            let color = *channel as u32;
+            *channel = if premultiply {
+                (color * alpha + 127) / 255
+            } else {
+                // Fully transparent texels become black.
+                (color * 255 + alpha / 2)
+                    .checked_div(alpha)
+                    .map_or(0, |color| color.min(255))
+            } as u8;
+        }
+    }
+}
+
+// Generates mip levels with a box filter, which is widened to 3 texels along odd sides, so that
+// edge texels contribute to the next level too. Texels are filtered as linear colors premultiplied
+// by alpha, so that transparent texels don't bleed into the opaque ones and sRGB colors are
+// averaged correctly. The levels keep the alpha mode of the base one. Only 8-bit formats
+// are supported, for other ones the base level is used only.
+fn generate_mip_levels(
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    premultiplied: bool,
+) -> Vec<Vec<u8>> {
+    if !is_8bit_rgba(format) {
+        return Vec::new();
+    }
+
+    let srgb = is_srgb(format);
+    let mut previous = data
+        .chunks_exact(4)
+        .map(|texel| decode_texel(texel, srgb, premultiplied))
+        .collect::<Vec<_>>();
+    let mut mip_levels = Vec::new();
+    let (mut width, mut height) = (width as usize, height as usize);
+    while width > 1 || height > 1 {
+        let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+
+        let mut level = Vec::with_capacity(next_width * next_height);
+        for y in 0..next_height {
+            for x in 0..next_width {
+                let mut texel = [0.0; 4];
+                for &(y, y_weight) in &box_filter_taps(height, y) {
+                    for &(x, x_weight) in &box_filter_taps(width, x) {
+                        let weight = x_weight * y_weight;
+                        for (value, source) in texel.iter_mut().zip(&previous[y * width + x]) {
+                            *value += source * weight;
+                        }
+                    }
+                }
+                level.push(texel);
+            }
+        }
+
+        let mut level_data = Vec::with_capacity(level.len() * 4);
+        for texel in &level {
+            level_data.extend_from_slice(&encode_texel(*texel, srgb, premultiplied));
+        }
+        mip_levels.push(level_data);
+        // The next level is filtered from unrounded values.
+        previous = level;
+        width = next_width;
+        height = next_height;
+    }
+    mip_levels
+}
+
+// Converts an 8-bit texel to a linear color premultiplied by alpha.
+fn decode_texel(texel: &[u8], srgb: bool, premultiplied: bool) -> [f32; 4] {
+    let alpha = texel[3] as f32 / 255.0;
+    let scale = if premultiplied { 1.0 } else { alpha };
+    let decode = |channel: u8| {
+        let color = channel as f32 / 255.0;
+        if srgb {
+            srgb_to_linear(color) * scale
+        } else {
+            color * scale
+        }
+    };
+    [decode(texel[0]), decode(texel[1]), decode(texel[2]), alpha]
+}
+
+// Converts a linear color premultiplied by alpha back to an 8-bit texel.
+fn encode_texel(texel: [f32; 4], srgb: bool, premultiplied: bool) -> [u8; 4] {
+    let alpha = texel[3];
+    let encode = |color: f32| {
+        let color = if premultiplied {
+            color
+        } else if alpha > 0.0 {
+            color / alpha
+        } else {
+            // Fully transparent texels become black.
+            0.0
+        };
+        let color = color.max(0.0).min(1.0);
+        let color = if srgb { linear_to_srgb(color) } else { color };
+        (color * 255.0 + 0.5) as u8
+    };
+    [
+        encode(texel[0]),
+        encode(texel[1]),
+        encode(texel[2]),
+        (alpha.min(1.0) * 255.0 + 0.5) as u8,
+    ]
+}
+
+fn is_srgb(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::Rgba8UnormSrgb | TextureFormat::Bgra8UnormSrgb
+    )
+}
+
+fn srgb_to_linear(color: f32) -> f32 {
+    if color <= 0.04045 {
+        color / 12.92
+    } else {
+        ((color + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(color: f32) -> f32 {
+    if color <= 0.003_130_8 {
+        color * 12.92
+    } else {
+        1.055 * color.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Returns indices and weights of the texels that a texel of the next mip level covers along a side.
+// An odd side of 2n + 1 texels is reduced to n texels, each of them covers a texel and two partial
+// ones, which are weighted by coverage.
+fn box_filter_taps(size: usize, i: usize) -> [(usize, f32); 3] {
+    if size == 1 {
+        [(0, 1.0), (0, 0.0), (0, 0.0)]
+    } else if size % 2 == 0 {
+        [(i * 2, 0.5), (i * 2 + 1, 0.5), (i * 2, 0.0)]
+    } else {
+        let (n, size) = ((size / 2) as f32, size as f32);
+        [
+            (i * 2, (n - i as f32) / size),
+            (i * 2 + 1, n / size),
+            (i * 2 + 2, (i as f32 + 1.0) / size),
+        ]
+    }
+}
+
+fn convert_format(
+    format: TextureFormat,
+    data: &[u8],
+    swizzle: MegaUiTextureSwizzle,
+) -> Result<(TextureFormat, Vec<u8>), TextureDataError> {
+    match format {
+        TextureFormat::R8Unorm => {
+            let mut converted = Vec::with_capacity(data.len() * 4);
+            for &r in data {
+                converted.extend_from_slice(&swizzle.expand_r(r));
+            }
+            Ok((TextureFormat::Rgba8Unorm, converted))
+        }
+        TextureFormat::Rg8Unorm => {
+            let mut converted = Vec::with_capacity(data.len() * 2);
+            for rg in data.chunks_exact(2) {
+                converted.extend_from_slice(&swizzle.expand_rg(rg[0], rg[1]));
+            }
+            Ok((TextureFormat::Rgba8Unorm, converted))
+        }
+        // Samplers take care of decoding sRGB and swizzling BGRA.
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb
+        | TextureFormat::Rgba16Float => Ok((format, data.to_vec())),
+        _ => Err(TextureDataError::UnsupportedFormat(format)),
+    }
+}
+
+impl MegaUiTextureSwizzle {
+    fn expand_r(self, r: u8) -> [u8; 4] {
+        match self {
+            MegaUiTextureSwizzle::Color => [r, r, r, u8::MAX],
+            MegaUiTextureSwizzle::AlphaMask => [u8::MAX, u8::MAX, u8::MAX, r],
+        }
+    }
+
+    fn expand_rg(self, r: u8, g: u8) -> [u8; 4] {
+        match self {
+            MegaUiTextureSwizzle::Color => [r, g, 0, u8::MAX],
+            MegaUiTextureSwizzle::AlphaMask => [r, r, r, g],
+        }
+    }
+}
+
+/// Copies tightly packed rows to a buffer with rows padded to `aligned_row_size` bytes,
+/// as copying buffers to textures requires.
+pub fn align_rows(data: &[u8], row_size: usize, aligned_row_size: usize) -> Vec<u8> {
+    if row_size == aligned_row_size {
+        return data.to_vec();
+    }
+
+    let mut aligned_data = vec![0; aligned_row_size * (data.len() / row_size)];
+    for (index, row) in data.chunks_exact(row_size).enumerate() {
+        let offset = index * aligned_row_size;
+        aligned_data[offset..offset + row_size].copy_from_slice(row);
+    }
+    aligned_data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::texture;
+
+    #[test]
+    fn test_align_rows() {
+        let data = [1, 2, 3, 4, 5, 6];
+        assert_eq!(align_rows(&data, 3, 3), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(align_rows(&data, 3, 4), vec![1, 2, 3, 0, 4, 5, 6, 0]);
+        assert_eq!(align_rows(&data, 2, 4).len(), 12);
+    }
+
+    #[test]
+    fn test_mipmaps() {
+        #[rustfmt::skip]
+        let data = vec![
+            0, 0, 0, 0,   4, 4, 4, 4,   100, 0, 0, 255,
+            8, 8, 8, 8,   12, 12, 12, 12,   200, 0, 0, 255,
+        ];
+        let rgba = texture(3, 2, 1, TextureFormat::Rgba8Unorm, data.clone());
+        let params = MegaUiTextureParams {
+            mipmaps: true,
+            ..Default::default()
+        };
+
+        let texture_data = prepare_texture_data(&rgba, &params, false).unwrap();
+        assert_eq!(texture_data.descriptor.mip_level_count, 2);
+        assert_eq!(texture_data.data, data);
+        // The last column is weighted the same as the other ones, and the nearly transparent
+        // texels don't darken the opaque ones.
+        assert_eq!(texture_data.mip_levels, vec![vec![144, 0, 0, 89]]);
+        assert_eq!(mip_level_size(3, 2, 1), (1, 1));
+        assert_eq!(mip_level_size(3, 2, 2), (1, 1));
+
+        let r8 = texture(4, 1, 1, TextureFormat::R8Unorm, vec![0, 255, 100, 200]);
+        let texture_data = prepare_texture_data(&r8, &params, false).unwrap();
+        assert_eq!(
+            texture_data.mip_levels,
+            vec![
+                vec![128, 128, 128, 255, 150, 150, 150, 255],
+                vec![139, 139, 139, 255]
+            ]
+        );
+
+        let r8 = texture(5, 1, 1, TextureFormat::R8Unorm, vec![0, 0, 0, 0, 255]);
+        let texture_data = prepare_texture_data(&r8, &params, false).unwrap();
+        assert_eq!(
+            texture_data.mip_levels,
+            vec![
+                vec![0, 0, 0, 255, 102, 102, 102, 255],
+                vec![51, 51, 51, 255]
+            ]
+        );
+
+        let rgba16 = texture(2, 2, 1, TextureFormat::Rgba16Float, vec![0; 32]);
+        let texture_data = prepare_texture_data(&rgba16, &params, false).unwrap();
+        assert_eq!(texture_data.descriptor.mip_level_count, 1);
+        assert!(texture_data.mip_levels.is_empty());
+    }
+
+    #[test]
+    fn test_transparent_checker_mipmaps() {
+        #[rustfmt::skip]
+        let data = vec![
+            255, 255, 255, 255,   0, 0, 0, 0,
+            0, 0, 0, 0,           255, 255, 255, 255,
+        ];
+        let checker = texture(2, 2, 1, TextureFormat::Rgba8Unorm, data);
+        let params = MegaUiTextureParams {
+            mipmaps: true,
+            ..Default::default()
+        };
+
+        // Straight colors keep the color of the opaque texels.
+        let texture_data = prepare_texture_data(&checker, &params, false).unwrap();
+        assert_eq!(texture_data.mip_levels, vec![vec![255, 255, 255, 128]]);
+        let texture_data = prepare_texture_data(&checker, &params, true).unwrap();
+        assert_eq!(texture_data.mip_levels, vec![vec![128, 128, 128, 128]]);
+    }
+
+    #[test]
+    fn test_srgb_mipmaps() {
+        #[rustfmt::skip]
+        let data = vec![
+            0, 0, 0, 255,   255, 255, 255, 255,
+        ];
+        let gradient = texture(2, 1, 1, TextureFormat::Rgba8UnormSrgb, data);
+        let params = MegaUiTextureParams {
+            mipmaps: true,
+            ..Default::default()
+        };
+
+        // The linear mid-gray is encoded as 188 rather than 128.
+        let texture_data = prepare_texture_data(&gradient, &params, false).unwrap();
+        assert_eq!(texture_data.mip_levels, vec![vec![188, 188, 188, 255]]);
+    }
+
+    #[test]
+    fn test_r8_swizzle() {
+        let texture = texture(2, 1, 1, TextureFormat::R8Unorm, vec![10, 20]);
+
+        let color = prepare_texture_data(&texture, &MegaUiTextureParams::default(), false).unwrap();
+        assert_eq!(color.descriptor.format, TextureFormat::Rgba8Unorm);
+        assert_eq!(color.data, vec![10, 10, 10, 255, 20, 20, 20, 255]);
+
+        let params = MegaUiTextureParams {
+            swizzle: MegaUiTextureSwizzle::AlphaMask,
+            ..Default::default()
+        };
+        let alpha_mask = prepare_texture_data(&texture, &params, false).unwrap();
+        assert_eq!(alpha_mask.data, vec![255, 255, 255, 10, 255, 255, 255, 20]);
+    }
+
+    #[test]
+    fn test_rg8_swizzle() {
+        let texture = texture(1, 2, 1, TextureFormat::Rg8Unorm, vec![1, 2, 3, 4]);
+
+        let color = prepare_texture_data(&texture, &MegaUiTextureParams::default(), false).unwrap();
+        assert_eq!(color.descriptor.format, TextureFormat::Rgba8Unorm);
+        assert_eq!(color.data, vec![1, 2, 0, 255, 3, 4, 0, 255]);
+
+        let params = MegaUiTextureParams {
+            swizzle: MegaUiTextureSwizzle::AlphaMask,
+            ..Default::default()
+        };
+        let alpha_mask = prepare_texture_data(&texture, &params, false).unwrap();
+        assert_eq!(alpha_mask.data, vec![1, 1, 1, 2, 3, 3, 3, 4]);
+    }
+
+    #[test]
+    fn test_pass_through_formats() {
+        for &format in &[
+            TextureFormat::Rgba8UnormSrgb,
+            TextureFormat::Bgra8UnormSrgb,
+            TextureFormat::Rgba16Float,
+        ] {
+            let data = (0..format.pixel_size() as u8 * 4).collect::<Vec<_>>();
+            let texture = texture(2, 2, 1, format, data.clone());
+            let texture_data =
+                prepare_texture_data(&texture, &MegaUiTextureParams::default(), false).unwrap();
+            assert_eq!(texture_data.descriptor.format, format);
+            assert_eq!(texture_data.data, data);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        let texture = texture(1, 1, 1, TextureFormat::R32Float, vec![0; 4]);
+        assert_eq!(
+            prepare_texture_data(&texture, &MegaUiTextureParams::default(), false),
+            Err(TextureDataError::UnsupportedFormat(TextureFormat::R32Float))
+        );
+    }
+
+    #[test]
+    fn test_layers() {
+        let texture = texture(1, 2, 3, TextureFormat::R8Unorm, vec![1, 2, 3, 4, 5, 6]);
+
+        let params = MegaUiTextureParams {
+            layer: 1,
+            swizzle: MegaUiTextureSwizzle::AlphaMask,
+            ..Default::default()
+        };
+        let texture_data = prepare_texture_data(&texture, &params, false).unwrap();
+        assert_eq!(texture_data.descriptor.size, Extent3d::new(1, 2, 1));
+        assert_eq!(texture_data.data, vec![255, 255, 255, 3, 255, 255, 255, 4]);
+
+        let params = MegaUiTextureParams {
+            layer: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            prepare_texture_data(&texture, &params, false),
+            Err(TextureDataError::LayerOutOfBounds {
+                layer: 3,
+                layers: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_region_from_rect() {
+        assert_eq!(
+            TextureRegion::from_rect(megaui::Rect::new(1.5, 2.0, 2.0, 1.0), 8, 8),
+            Some(TextureRegion {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 1
+            })
+        );
+        assert_eq!(
+            TextureRegion::from_rect(megaui::Rect::new(-2.0, 6.0, 4.0, 10.0), 8, 8),
+            Some(TextureRegion {
+                x: 0,
+                y: 6,
+                width: 2,
+                height: 2
+            })
+        );
+        assert_eq!(
+            TextureRegion::from_rect(megaui::Rect::new(8.0, 0.0, 2.0, 2.0), 8, 8),
+            None
+        );
+        assert_eq!(
+            TextureRegion::from_rect(megaui::Rect::new(1.0, 1.0, 0.0, 2.0), 8, 8),
+            None
+        );
+    }
+
+    #[test]
+    fn test_region_data() {
+        let texture = texture(3, 3, 1, TextureFormat::R8Unorm, (1..=9).collect());
+        let region = TextureRegion {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+
+        let params = MegaUiTextureParams {
+            swizzle: MegaUiTextureSwizzle::AlphaMask,
+            ..Default::default()
+        };
+        let texture_data = prepare_texture_region_data(&texture, &params, false, region).unwrap();
+        assert_eq!(
+            texture_data.descriptor,
+            prepare_texture_data(&texture, &params, false)
+                .unwrap()
+                .descriptor
+        );
+        #[rustfmt::skip]
+        let expected = vec![
+            255, 255, 255, 5,   255, 255, 255, 6,
+            255, 255, 255, 8,   255, 255, 255, 9,
+        ];
+        assert_eq!(texture_data.data, expected);
+
+        let params = MegaUiTextureParams {
+            mipmaps: true,
+            ..Default::default()
+        };
+        let texture_data = prepare_texture_region_data(&texture, &params, false, region).unwrap();
+        assert_eq!(
+            texture_data.descriptor,
+            prepare_texture_data(&texture, &params, false)
+                .unwrap()
+                .descriptor
+        );
+        assert!(texture_data.mip_levels.is_empty());
+    }
+
+    #[test]
+    fn test_not_enough_data() {
+        let texture = Texture {
+            data: vec![0; 3],
+            ..texture(1, 1, 1, TextureFormat::Rgba8Unorm, vec![0; 4])
+        };
+        assert_eq!(
+            prepare_texture_data(&texture, &MegaUiTextureParams::default(), false),
+            Err(TextureDataError::NotEnoughData {
+                expected: 4,
+                actual: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_alpha_conversion() {
+        let straight = texture(
+            2,
+            1,
+            1,
+            TextureFormat::Rgba8Unorm,
+            vec![200, 100, 0, 128, 9, 9, 9, 0],
+        );
+        let premultiplied =
+            prepare_texture_data(&straight, &MegaUiTextureParams::default(), true).unwrap();
+        assert_eq!(premultiplied.data, vec![100, 50, 0, 128, 0, 0, 0, 0]);
+
+        let params = MegaUiTextureParams {
+            premultiplied: true,
+            ..Default::default()
+        };
+        let texture = texture(2, 1, 1, TextureFormat::Rgba8Unorm, premultiplied.data);
+        let texture_data = prepare_texture_data(&texture, &params, false).unwrap();
+        assert_eq!(texture_data.data, vec![199, 100, 0, 128, 0, 0, 0, 0]);
+        let texture_data = prepare_texture_data(&texture, &params, true).unwrap();
+        assert_eq!(texture_data.data, vec![100, 50, 0, 128, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_srgb_alpha_conversion() {
+        let straight = texture(
+            2,
+            1,
+            1,
+            TextureFormat::Rgba8UnormSrgb,
+            vec![255, 188, 0, 128, 9, 9, 9, 0],
+        );
+        // The encoded colors are scaled less than the linear ones.
+        let premultiplied =
+            prepare_texture_data(&straight, &MegaUiTextureParams::default(), true).unwrap();
+        assert_eq!(premultiplied.data, vec![188, 138, 0, 128, 0, 0, 0, 0]);
+
+        let params = MegaUiTextureParams {
+            premultiplied: true,
+            ..Default::default()
+        };
+        let texture = texture(2, 1, 1, TextureFormat::Rgba8UnormSrgb, premultiplied.data);
+        let texture_data = prepare_texture_data(&texture, &params, false).unwrap();
+        assert_eq!(texture_data.data, vec![255, 189, 0, 128, 0, 0, 0, 0]);
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{MegaUiSettings, WindowSize, MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME};
+use crate::{
+    window_resource_binding_name, MegaUiAffineTransform, MegaUiPipelineConfig, MegaUiSettings,
+    MegaUiViewport, WindowSize, WindowSizes, MEGAUI_AFFINE_TRANSFORM_RESOURCE_BINDING_NAME,
+    MEGAUI_COLOR_TRANSFORM_RESOURCE_BINDING_NAME, MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME,
+};
 use bevy::{
     core::AsBytes,
     ecs::{Commands, IntoSystem, Local, Res, ResMut, Resources, System, World},
@@ -9,16 +13,19 @@ use bevy::{
             RenderResourceBindings, RenderResourceContext,
         },
     },
+    window::WindowId,
 };
 
 #[derive(Debug)]
 pub struct MegaUiTransformNode {
+    window_id: WindowId,
     command_queue: CommandQueue,
 }
 
 impl MegaUiTransformNode {
-    pub fn new() -> Self {
+    pub fn new(window_id: WindowId) -> Self {
         MegaUiTransformNode {
+            window_id,
             command_queue: Default::default(),
         }
     }
@@ -43,11 +50,17 @@ impl SystemNode for MegaUiTransformNode {
         commands.insert_local_resource(
             system.id(),
             TransformNodeState {
+                window_id: self.window_id,
                 command_queue: self.command_queue.clone(),
                 transform_buffer: None,
-                staging_buffer: None,
+                affine_transform_buffer: None,
+                color_transform_buffer: None,
                 prev_window_size: WindowSize::new(0.0, 0.0, 0.0),
                 prev_scale_factor: 0.0,
+                prev_viewport: MegaUiViewport::default(),
+                prev_transform: MegaUiAffineTransform::IDENTITY,
+                prev_color_scale: [0.0; 4],
+                prev_color_matrix: [0.0; 16],
             },
         );
         Box::new(system)
@@ -56,83 +69,220 @@ impl SystemNode for MegaUiTransformNode {
 
 #[derive(Default)]
 pub struct TransformNodeState {
+    window_id: WindowId,
     command_queue: CommandQueue,
-    transform_buffer: Option<BufferId>,
-    staging_buffer: Option<BufferId>,
+    transform_buffer: Option<UniformBuffer>,
+    affine_transform_buffer: Option<UniformBuffer>,
+    color_transform_buffer: Option<UniformBuffer>,
     prev_window_size: WindowSize,
     prev_scale_factor: f64,
+    prev_viewport: MegaUiViewport,
+    prev_transform: MegaUiAffineTransform,
+    prev_color_scale: [f32; 4],
+    prev_color_matrix: [f32; 16],
 }
 
 fn transform_node_system(
     mut state: Local<TransformNodeState>,
     render_resource_context: Res<Box<dyn RenderResourceContext>>,
-    window_size: Res<WindowSize>,
+    window_sizes: Res<WindowSizes>,
     megaui_settings: Res<MegaUiSettings>,
+    megaui_pipeline_config: Res<MegaUiPipelineConfig>,
     mut render_resource_bindings: ResMut<RenderResourceBindings>,
 ) {
+    let window_size = window_sizes.get(state.window_id);
+    let viewport = megaui_settings.viewport(state.window_id, &window_size);
+    let transform = megaui_settings.transform(state.window_id);
+    // Colors are multiplied by the color scale, premultiplied colors are scaled as a whole.
+    let opacity = megaui_settings.opacity();
+    let color_scale = if megaui_pipeline_config.blend_mode.premultiplied_alpha() {
+        [opacity; 4]
+    } else {
+        [1.0, 1.0, 1.0, opacity]
+    };
+    let color_matrix = color_matrix(&megaui_settings, &megaui_pipeline_config);
     #[allow(clippy::float_cmp)]
-    if state.prev_window_size == *window_size
+    if state.prev_window_size == window_size
         && state.prev_scale_factor == megaui_settings.scale_factor
+        && state.prev_viewport == viewport
+        && state.prev_transform == transform
+        && state.prev_color_scale == color_scale
+        && state.prev_color_matrix == color_matrix
     {
         return;
     }
     state.prev_window_size = window_size.clone();
     state.prev_scale_factor = megaui_settings.scale_factor;
+    state.prev_viewport = viewport;
+    state.prev_transform = transform;
+    state.prev_color_scale = color_scale;
+    state.prev_color_matrix = color_matrix;
 
     let render_resource_context = &**render_resource_context;
-    let transform_data_size = std::mem::size_of::<[[f32; 2]; 2]>();
+    let TransformNodeState {
+        window_id,
+        command_queue,
+        transform_buffer,
+        affine_transform_buffer,
+        color_transform_buffer,
+        ..
+    } = &mut *state;
 
-    let staging_buffer = if let Some(staging_buffer) = state.staging_buffer {
-        render_resource_context.map_buffer(staging_buffer);
-        staging_buffer
-    } else {
+    // The transform maps logical UI units to normalized device coordinates, so it doesn't depend
+    // on the render target resolution (see `MegaUiSettings::resolution_scale`).
+    // The UI origin is moved to the top-left corner of the viewport.
+    let transform_data = [
+        2.0 / (window_size.width() / megaui_settings.scale_factor as f32),
+        -2.0 / (window_size.height() / megaui_settings.scale_factor as f32), // scale
+        -1.0 + 2.0 * viewport.x / window_size.width(),
+        1.0 - 2.0 * viewport.y / window_size.height(), // translation
+    ];
+    transform_buffer
+        .get_or_insert_with(|| {
+            UniformBuffer::new(
+                render_resource_context,
+                &mut render_resource_bindings,
+                MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME,
+                *window_id,
+                transform_data.len(),
+            )
+        })
+        .write(render_resource_context, command_queue, &transform_data);
+
+    // The block is padded to the size of two `vec4`s.
+    let affine_transform_data = [
+        transform.x_axis[0],
+        transform.x_axis[1], // ui_x_axis
+        transform.y_axis[0],
+        transform.y_axis[1], // ui_y_axis
+        transform.translation[0],
+        transform.translation[1], // ui_translation
+        0.0,
+        0.0,
+    ];
+    affine_transform_buffer
+        .get_or_insert_with(|| {
+            UniformBuffer::new(
+                render_resource_context,
+                &mut render_resource_bindings,
+                MEGAUI_AFFINE_TRANSFORM_RESOURCE_BINDING_NAME,
+                *window_id,
+                affine_transform_data.len(),
+            )
+        })
+        .write(
+            render_resource_context,
+            command_queue,
+            &affine_transform_data,
+        );
+
+    let mut color_transform_data = [0.0; 20];
+    color_transform_data[..4].copy_from_slice(&color_scale); // ui_color_scale
+    color_transform_data[4..].copy_from_slice(&color_matrix); // ui_color_matrix
+    color_transform_buffer
+        .get_or_insert_with(|| {
+            UniformBuffer::new(
+                render_resource_context,
+                &mut render_resource_bindings,
+                MEGAUI_COLOR_TRANSFORM_RESOURCE_BINDING_NAME,
+                *window_id,
+                color_transform_data.len(),
+            )
+        })
+        .write(
+            render_resource_context,
+            command_queue,
+            &color_transform_data,
+        );
+}
+
+// A uniform buffer that is bound under a window resource binding name and written through
+// a staging buffer.
+struct UniformBuffer {
+    buffer: BufferId,
+    staging_buffer: BufferId,
+    size: usize,
+}
+
+impl UniformBuffer {
+    fn new(
+        render_resource_context: &dyn RenderResourceContext,
+        render_resource_bindings: &mut RenderResourceBindings,
+        binding_name: &'static str,
+        window_id: WindowId,
+        len: usize,
+    ) -> Self {
+        let size = len * std::mem::size_of::<f32>();
         let buffer = render_resource_context.create_buffer(BufferInfo {
-            size: transform_data_size,
+            size,
             buffer_usage: BufferUsage::COPY_DST | BufferUsage::UNIFORM,
             ..Default::default()
         });
         render_resource_bindings.set(
-            MEGAUI_TRANSFORM_RESOURCE_BINDING_NAME,
+            &window_resource_binding_name(binding_name, window_id),
             RenderResourceBinding::Buffer {
                 buffer,
-                range: 0..transform_data_size as u64,
+                range: 0..size as u64,
                 dynamic_index: None,
             },
         );
-        state.transform_buffer = Some(buffer);
-
         let staging_buffer = render_resource_context.create_buffer(BufferInfo {
-            size: transform_data_size,
+            size,
             buffer_usage: BufferUsage::COPY_SRC | BufferUsage::MAP_WRITE,
-            mapped_at_creation: true,
+            ..Default::default()
         });
+        UniformBuffer {
+            buffer,
+            staging_buffer,
+            size,
+        }
+    }
 
-        state.staging_buffer = Some(staging_buffer);
-        staging_buffer
-    };
-
-    let transform_data: [f32; 4] = [
-        2.0 / (window_size.width() / megaui_settings.scale_factor as f32),
-        -2.0 / (window_size.height() / megaui_settings.scale_factor as f32), // scale
-        -1.0,
-        1.0, // translation
-    ];
-
-    render_resource_context.write_mapped_buffer(
-        staging_buffer,
-        0..transform_data_size as u64,
-        &mut |data, _renderer| {
-            data[0..transform_data_size].copy_from_slice(transform_data.as_bytes());
-        },
-    );
-    render_resource_context.unmap_buffer(staging_buffer);
+    fn write(
+        &self,
+        render_resource_context: &dyn RenderResourceContext,
+        command_queue: &mut CommandQueue,
+        data: &[f32],
+    ) {
+        let size = self.size;
+        render_resource_context.map_buffer(self.staging_buffer);
+        render_resource_context.write_mapped_buffer(
+            self.staging_buffer,
+            0..size as u64,
+            &mut |buffer_data, _renderer| {
+                buffer_data[0..size].copy_from_slice(data.as_bytes());
+            },
+        );
+        render_resource_context.unmap_buffer(self.staging_buffer);
+        command_queue.copy_buffer_to_buffer(self.staging_buffer, 0, self.buffer, 0, size as u64);
+    }
+}
 
-    let transform_buffer = state.transform_buffer.unwrap();
-    state.command_queue.copy_buffer_to_buffer(
-        staging_buffer,
-        0,
-        transform_buffer,
-        0,
-        transform_data_size as u64,
+// Returns the color filter matrix in the column-major order. The last column is an offset, its last
+// component tells the shaders whether colors are premultiplied (see `megaui.frag`).
+fn color_matrix(
+    megaui_settings: &MegaUiSettings,
+    megaui_pipeline_config: &MegaUiPipelineConfig,
+) -> [f32; 16] {
+    let rows = megaui_settings.color_filter.map_or(
+        [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+        ],
+        |color_filter| color_filter.matrix(),
     );
+    let premultiplied = if megaui_pipeline_config.blend_mode.premultiplied_alpha() {
+        1.0
+    } else {
+        0.0
+    };
+    let mut color_matrix = [0.0; 16];
+    for (column, values) in color_matrix.chunks_exact_mut(4).enumerate() {
+        values[0] = rows[0][column];
+        values[1] = rows[1][column];
+        values[2] = rows[2][column];
+    }
+    color_matrix[15] = premultiplied;
+    color_matrix
 }
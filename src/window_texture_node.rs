@@ -0,0 +1,97 @@
+use bevy::{
+    ecs::{Resources, World},
+    render::{
+        render_graph::{base::Msaa, Node, ResourceSlotInfo, ResourceSlots},
+        renderer::{RenderContext, RenderResourceId, RenderResourceType},
+        texture::TextureDescriptor,
+    },
+    window::{WindowId, Windows},
+};
+use std::borrow::Cow;
+
+/// Works like `WindowTextureNode`, but takes the sample count from the [Msaa] resource and
+/// re-creates the texture when it changes.
+pub struct MegaUiWindowTextureNode {
+    window_id: WindowId,
+    descriptor: TextureDescriptor,
+    multisampled_only: bool,
+}
+
+impl MegaUiWindowTextureNode {
+    pub const OUT_TEXTURE: &'static str = "texture";
+
+    pub fn new(window_id: WindowId, descriptor: TextureDescriptor) -> Self {
+        MegaUiWindowTextureNode {
+            window_id,
+            descriptor,
+            multisampled_only: false,
+        }
+    }
+
+    /// Creates a node of a texture that is used only if MSAA is enabled, a 1x1 texture is
+    /// created instead of a window-sized one otherwise.
+    pub fn multisampled(window_id: WindowId, descriptor: TextureDescriptor) -> Self {
+        MegaUiWindowTextureNode {
+            window_id,
+            descriptor,
+            multisampled_only: true,
+        }
+    }
+}
+
+impl Node for MegaUiWindowTextureNode {
+    fn output(&self) -> &[ResourceSlotInfo] {
+        static OUTPUT: &[ResourceSlotInfo] = &[ResourceSlotInfo {
+            name: Cow::Borrowed(MegaUiWindowTextureNode::OUT_TEXTURE),
+            resource_type: RenderResourceType::Texture,
+        }];
+        OUTPUT
+    }
+
+    fn update(
+        &mut self,
+        _world: &World,
+        resources: &Resources,
+        render_context: &mut dyn RenderContext,
+        _input: &ResourceSlots,
+        output: &mut ResourceSlots,
+    ) {
+        const WINDOW_TEXTURE: usize = 0;
+
+        let windows = resources.get::<Windows>().unwrap();
+        let window = match windows.get(self.window_id) {
+            Some(window) => window,
+            None => return,
+        };
+        let msaa = resources.get::<Msaa>().unwrap();
+
+        let (width, height) = if self.multisampled_only && msaa.samples == 1 {
+            (1, 1)
+        } else {
+            (window.physical_width(), window.physical_height())
+        };
+        // Minimized windows can have zero size, we can't create textures for them.
+        if width == 0 || height == 0 {
+            return;
+        }
+        let size = self.descriptor.size;
+        if output.get(WINDOW_TEXTURE).is_some()
+            && size.width == width
+            && size.height == height
+            && self.descriptor.sample_count == msaa.samples
+        {
+            return;
+        }
+
+        let render_resource_context = render_context.resources_mut();
+        if let Some(RenderResourceId::Texture(old_texture)) = output.get(WINDOW_TEXTURE) {
+            render_resource_context.remove_texture(old_texture);
+        }
+
+        self.descriptor.size.width = width;
+        self.descriptor.size.height = height;
+        self.descriptor.sample_count = msaa.samples;
+        let texture = render_resource_context.create_texture(self.descriptor);
+        output.set(WINDOW_TEXTURE, RenderResourceId::Texture(texture));
+    }
+}